@@ -24,7 +24,7 @@ pub fn game_screen() -> Result<GameScreen, Error> {
         _ => None,
     });
     if target.is_none() {
-        return Err(Error::CapturerError("target not found".to_string()));
+        return Err(Error::TargetNotFound);
     }
     let target = target.unwrap();
     let dimension = targets::get_target_dimensions(&target);
@@ -46,7 +46,7 @@ pub fn live_capture(config: engine::ScreenConfig) -> Result<capturer::Capturer,
         _ => None,
     });
     if target.is_none() {
-        return Err(Error::CapturerError("target not found".to_string()));
+        return Err(Error::TargetNotFound);
     }
     let target = target.unwrap();
     let area = config.capture_area;
@@ -7,32 +7,55 @@ use serde::{Deserialize, Serialize};
 use std::hash::Hash;
 use std::io::{BufWriter, Write};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 use std::{collections::HashMap, fs::File};
 use tokio::sync::{Mutex, mpsc, watch};
+use tokio::task::{JoinHandle, JoinSet};
 use tokio::time;
 
 use crate::ocr::OcrOutput;
 use crate::{
-    core::{IFrameCapturer, capturer, error, game_screen},
-    engine::{BlackDesertLootTracker, LootData, LootDetectionMode, Screen},
+    core::{
+        Backoff, ErrorKind, FrameQueue, IFrameCapturer, OcrHealth, PipelineConfig, PipelineStatus,
+        ReorderBuffer, SupervisorConfig, capturer, error, game_screen,
+    },
+    engine::{
+        BlackDesertLootTracker, Disambiguator, Fetcher, HourlyCost, Language, LootData,
+        LootDetectionMode, LootTrackerHandle, PendingChoice, PriceRules, Region, Screen, Session,
+        default_fetcher,
+    },
     ocr::{self, OcrClient, OcrInput},
 };
 
-#[derive(Clone)]
-enum CoreStatus {
+// Initiated -> Started <-> Paused -> Stopped, a stopped core can be started again
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CoreStatus {
     Initiated,
     Started,
+    Paused,
     Stopped,
 }
 
+// how long stop waits for the in flight ocr before aborting the capture loop
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(10);
+const PAUSE_POLL_INTERVAL: Duration = Duration::from_millis(250);
+const SESSION_DIR: &str = "sessions";
+
 #[derive(Clone)]
 pub struct Core {
-    loot_tracker: Arc<Mutex<BlackDesertLootTracker>>,
+    loot_tracker: LootTrackerHandle,
     ocr_client: Arc<OcrClient>,
     loot_sender: watch::Sender<HashMap<String, LootData>>,
     capturer: Option<Arc<Mutex<scap::capturer::Capturer>>>,
     pub game_screen: GameScreen,
     status: Arc<Mutex<CoreStatus>>,
+    capture_task: Arc<Mutex<Option<JoinHandle<()>>>>,
+    // true from `stop` until the next `start`, wakes the capture tasks that are waiting
+    stop_signal: watch::Sender<bool>,
+    pipeline: PipelineConfig,
+    supervisor: SupervisorConfig,
+    pipeline_status: watch::Sender<PipelineStatus>,
+    disambiguator: Arc<Disambiguator>,
 }
 
 #[derive(Clone, Copy)]
@@ -44,13 +67,56 @@ pub struct GameScreen {
 }
 struct OcrChannel {
     index: u64,
+    // unix millis the frame was captured, the drops in it happened before that
+    captured_at: i64,
     result: Option<OcrOutput>,
     err: Option<error::Error>,
 }
 
+struct FrameJob {
+    index: u64,
+    captured_at: i64,
+    frame: scap::frame::RGBFrame,
+}
+
+async fn ocr_worker(
+    ocr_client: Arc<OcrClient>,
+    queue: Arc<FrameQueue<FrameJob>>,
+    sender: mpsc::Sender<OcrChannel>,
+) {
+    while let Some(job) = queue.pop().await {
+        let result = ocr_client
+            .do_ocr(OcrInput {
+                data: job.frame.data,
+                width: job.frame.width,
+                height: job.frame.height,
+            })
+            .await
+            .map_err(|e| error::Error::OcrError(e.to_string()));
+        let msg = match result {
+            Ok(output) => OcrChannel {
+                index: job.index,
+                captured_at: job.captured_at,
+                result: Some(output),
+                err: None,
+            },
+            Err(err) => OcrChannel {
+                index: job.index,
+                captured_at: job.captured_at,
+                result: None,
+                err: Some(err),
+            },
+        };
+        if sender.send(msg).await.is_err() {
+            break;
+        }
+    }
+}
+
 impl Core {
     pub fn new() -> Result<Self, error::Error> {
         let loot_tracker = BlackDesertLootTracker::new();
+        let disambiguator = loot_tracker.disambiguator.clone();
         // let config = loot_tracker.stream_config.clone();
         // let capturer = live_capture(config);
         // if let Err(capturer) = capturer {
@@ -61,98 +127,246 @@ impl Core {
 
         // Create channel for loot data updates (initialized with empty map)
         let (loot_sender, _) = watch::channel(HashMap::new());
+        let (pipeline_status, _) = watch::channel(PipelineStatus::Idle);
+        let (stop_signal, _) = watch::channel(false);
         let game_screen = game_screen()?;
         Ok(Self {
-            loot_tracker: Arc::new(Mutex::new(loot_tracker)),
+            loot_tracker: LootTrackerHandle::spawn(loot_tracker, loot_sender.clone()),
             ocr_client: Arc::new(ocr_client),
             loot_sender,
             // mutex: Arc::new(Mutex::new(0)),
             capturer: None,
             game_screen: game_screen,
             status: Arc::new(Mutex::new(CoreStatus::Initiated)),
+            capture_task: Arc::new(Mutex::new(None)),
+            stop_signal,
+            pipeline: PipelineConfig::default(),
+            supervisor: SupervisorConfig::default(),
+            pipeline_status,
+            disambiguator,
         })
     }
     pub fn default() {}
     pub async fn use_chatlog(&mut self) {
-        let mut tracker = self.loot_tracker.lock().await;
-        tracker.detection_mode = LootDetectionMode::OCRChatLootViaStream
+        self.loot_tracker
+            .set_detection_mode(LootDetectionMode::OCRChatLootViaStream)
+            .await
+    }
+    // e.g. `Fetcher::ItemDb` to price loot without network
+    pub async fn use_item_fetcher(&mut self, fetcher: Fetcher) {
+        self.loot_tracker.set_item_fetcher(fetcher).await
+    }
+    // price lookups from now on go to this server's market, the chat and
+    // item names are read in the client's language
+    pub async fn use_region(&mut self, region: Region, language: Language) {
+        self.loot_tracker
+            .set_item_fetcher(default_fetcher(
+                region,
+                language,
+                self.disambiguator.clone(),
+            ))
+            .await;
+        self.loot_tracker.set_language(language).await
+    }
+    // names that matched several items and were picked without being sure
+    pub fn pending_item_choices(&self) -> Vec<PendingChoice> {
+        self.disambiguator.pending()
+    }
+    // remember which item this name means from now on
+    pub async fn confirm_item_choice(&self, name: &str, id: u64) -> Result<(), error::Error> {
+        self.disambiguator
+            .confirm(name, id)
+            .await
+            .map_err(|e| error::Error::UnknownError(e.to_string()))
+    }
+    pub async fn use_price_rules(&mut self, rules: PriceRules) {
+        self.loot_tracker.set_price_rules(rules).await
+    }
+    // only loot this spot drops is counted, None counts everything.
+    // either way the spot is no longer detected
+    pub async fn use_grind_spot(&mut self, name: Option<&str>) -> Result<(), error::Error> {
+        if self
+            .loot_tracker
+            .set_grind_spot(name.map(str::to_string))
+            .await
+        {
+            return Ok(());
+        }
+        Err(error::Error::InvalidState(format!(
+            "unknown grind spot {:?}",
+            name.unwrap_or_default()
+        )))
+    }
+    // guess the spot from the loot, the session is labeled with it
+    pub async fn detect_grind_spot(&mut self) {
+        self.loot_tracker.detect_grind_spot().await
+    }
+    // buffs and elixirs the chat doesn't show, subtracted from the profit per hour
+    pub async fn use_hourly_costs(&mut self, costs: Vec<HourlyCost>) {
+        self.loot_tracker.set_hourly_costs(costs).await
+    }
+    pub async fn grind_spots(&self) -> Vec<String> {
+        self.loot_tracker.grind_spots().await
     }
     pub async fn use_drop(&mut self) {
-        let mut tracker = self.loot_tracker.lock().await;
-        tracker.detection_mode = LootDetectionMode::OCRDropLogViaStream
+        self.loot_tracker
+            .set_detection_mode(LootDetectionMode::OCRDropLogViaStream)
+            .await
     }
     pub async fn start(&mut self) -> Result<(), error::Error> {
+        let current = self.status().await;
+        if !matches!(current, CoreStatus::Initiated | CoreStatus::Stopped) {
+            return Err(error::Error::InvalidState(format!(
+                "cannot start while {:?}",
+                current
+            )));
+        }
         // recapture into exact frame first
-        self.recapture_into_exact_frame().await?;
+        self.recapture_into_exact_frame(self.game_screen).await?;
+        {
+            let mut status = self.status.as_ref().lock().await;
+            *status = CoreStatus::Started;
+        }
+        self.stop_signal.send_replace(false);
 
         // Clone self for the background task
         let self_clone = self.clone();
 
         // Spawn the capture loop as a background task
-        tokio::spawn(async move {
+        let task = tokio::spawn(async move {
             self_clone.run_capture_loop().await;
         });
-        let mut status = self.status.as_ref().lock().await;
-        *status = CoreStatus::Started;
+        *self.capture_task.lock().await = Some(task);
+        Ok(())
+    }
+    // stop capturing, the loot table is kept until `reset`
+    pub async fn pause(&self) -> Result<(), error::Error> {
+        self.transition(CoreStatus::Started, CoreStatus::Paused)
+            .await
+    }
+    pub async fn resume(&self) -> Result<(), error::Error> {
+        self.transition(CoreStatus::Paused, CoreStatus::Started)
+            .await
+    }
+    // stop every task, process the ocr still in flight and save the session
+    pub async fn stop(&mut self) -> Result<(), error::Error> {
+        {
+            let mut status = self.status.lock().await;
+            if !matches!(*status, CoreStatus::Started | CoreStatus::Paused) {
+                return Err(error::Error::InvalidState(format!(
+                    "cannot stop while {:?}",
+                    *status
+                )));
+            }
+            *status = CoreStatus::Stopped;
+        }
+        self.stop_signal.send_replace(true);
+        // the producer sees Stopped and closes the queue, the workers finish what they have
+        // and the capture loop exits once every result is processed
+        let task = self.capture_task.lock().await.take();
+        if let Some(mut task) = task {
+            if time::timeout(SHUTDOWN_TIMEOUT, &mut task).await.is_err() {
+                // the workers and the producer are aborted with it, see `run_capture_loop`
+                task.abort();
+                _ = task.await;
+            }
+        }
+        if let Some(capturer) = &self.capturer {
+            capturer.lock().await.stop_capture();
+        }
+        self.pipeline_status.send_replace(PipelineStatus::Stopped);
+
+        let session = self.loot_tracker.session().await;
+        session
+            .save(SESSION_DIR)
+            .await
+            .map_err(|e| error::Error::UnknownError(e.to_string()))?;
         Ok(())
     }
-    pub async fn stop(&mut self) {
-        self.capturer.as_ref().unwrap().lock().await.stop_capture();
-        self.loot_tracker.lock().await.reset().await;
+    // the running session, not saved
+    pub async fn session(&self) -> Session {
+        self.loot_tracker.session().await
+    }
+    // clear the loot table and start a new session, works in any state
+    pub async fn reset(&self) {
+        self.loot_tracker.reset().await;
+    }
+    // look up the price of every loot that failed before, e.g. once the network is back
+    pub async fn retry_unpriced(&self) {
+        self.loot_tracker.retry_unpriced().await;
+    }
+    pub async fn status(&self) -> CoreStatus {
+        *self.status.lock().await
+    }
+    async fn transition(&self, from: CoreStatus, to: CoreStatus) -> Result<(), error::Error> {
+        let mut status = self.status.lock().await;
+        if *status != from {
+            return Err(error::Error::InvalidState(format!(
+                "cannot go from {:?} to {:?}",
+                *status, to
+            )));
+        }
+        *status = to;
+        Ok(())
     }
 
     async fn run_capture_loop(&self) {
-        // let mut empty_frame_count = 0;
-        // const MAX_EMPTY_FRAMES: usize = 10;
-        // const FRAME_INTERVAL: Duration = Duration::from_millis(500);
-        // game_screen();
         {
             self.capturer.as_ref().unwrap().lock().await.start_capture();
         }
-        let (sender, mut receiver) = mpsc::channel::<OcrChannel>(10);
-        let get_data_channel = self.clone();
-        tokio::spawn(async move {
-            _ = get_data_channel.get_data_channel(sender).await;
+        let config = self.pipeline;
+        let (sender, mut receiver) =
+            mpsc::channel::<OcrChannel>(config.queue_capacity + config.workers);
+        let queue = Arc::new(FrameQueue::<FrameJob>::new(config.queue_capacity));
+        // dropped with the capture loop, so aborting the loop on stop aborts these too
+        let mut tasks = JoinSet::new();
+        for _ in 0..config.workers.max(1) {
+            let ocr_client = self.ocr_client.clone();
+            let queue = queue.clone();
+            let sender = sender.clone();
+            tasks.spawn(async move {
+                ocr_worker(ocr_client, queue, sender).await;
+            });
+        }
+        let supervisor = self.clone();
+        tasks.spawn(async move {
+            supervisor.supervise_capture(queue, sender).await;
         });
-        let mut idx = 0;
-        let mut ordered_buffer: HashMap<u64, OcrChannel> = HashMap::new();
+        let mut ordered_buffer: ReorderBuffer<OcrChannel> =
+            ReorderBuffer::new(config.reorder_timeout);
+        let mut ocr_health = OcrHealth::new(self.supervisor.ocr_failure_threshold);
+        let mut tick = time::interval(time::Duration::from_millis(250));
         loop {
             tokio::select! {
-                Some(msg) = receiver.recv() => {
-                    if msg.index != idx{
-                        ordered_buffer.insert(msg.index, msg);
-                        continue
+                msg = receiver.recv() => {
+                    match msg {
+                        Some(msg) => ordered_buffer.insert(msg.index, msg),
+                        // producer and every worker are gone
+                        None => break,
                     }
-
-
-                     self.process_data(msg).await;
-
-                    idx +=1;
-                    loop{
-                        let data = ordered_buffer.remove(&idx);
-                        if data.is_none(){
-                            break
-                        }
-
-                        self.process_data(data.unwrap()).await;
-                        idx += 1;
-                    }
-
                 },
-                _ = time::sleep(time::Duration::from_secs(1)) => {
-                let status = self.status.lock().await;
-                match *status {
-                    CoreStatus::Stopped => break,
-                    _ => {}
-                };
-
+                // keep draining so a missing index times out even without new results
+                _ = tick.tick() => {}
+            }
+            for msg in ordered_buffer.drain(Instant::now()) {
+                if let Some(err) = &msg.err {
+                    if ocr_health.record_failure() {
+                        self.pipeline_status.send_replace(PipelineStatus::OcrDown {
+                            consecutive_failures: ocr_health.consecutive_failures(),
+                            reason: err.to_string(),
+                        });
+                    }
+                } else if msg.result.is_some() && ocr_health.record_success() {
+                    self.pipeline_status.send_replace(PipelineStatus::Running);
                 }
+                self.process_data(msg).await;
             }
-
-            // Add delay between captures to prevent CPU overload
-            // time::sleep(FRAME_INTERVAL).await;
         }
-        drop(receiver);
+        // producer and workers are done, whatever is still buffered won't get its gap filled
+        for msg in ordered_buffer.flush() {
+            self.process_data(msg).await;
+        }
+        tasks.join_all().await;
     }
 
     async fn process_data(&self, input: OcrChannel) {
@@ -175,10 +389,8 @@ impl Core {
 
         // let _ = self.mutex.lock().await;
 
-        let mut tracker = self.loot_tracker.lock().await;
-        tracker.insert(&texts).await;
-        // Send update to all receivers
-        let _ = self.loot_sender.send(tracker.get_loot_data().clone());
+        // the tracker sends the update to all receivers
+        self.loot_tracker.insert(texts, input.captured_at).await;
     }
 
     async fn get_data(&self) -> Result<ocr::OcrOutput, error::Error> {
@@ -236,110 +448,156 @@ impl Core {
         // img.save(format!("{}.png", chrono::Local::now().timestamp_millis()));
         return Ok(output);
     }
-    async fn get_data_channel(&self, sender: mpsc::Sender<OcrChannel>) -> Result<(), error::Error> {
-        // let frame = self
-        //     .capturer
-        //     .as_ref()
-        //     .unwrap()
-        //     .lock()
-        //     .await
-        //     .g()
-        //     .await;
-        // if let Err(frame) = frame {
-        //     return Err(frame);
-        // }
-        // let frame = frame.unwrap();
-
-        // let _guard = self.mutex.lock().await;
+    // keep the producer alive, rebuild the capturer when it fails
+    async fn supervise_capture(
+        &self,
+        queue: Arc<FrameQueue<FrameJob>>,
+        sender: mpsc::Sender<OcrChannel>,
+    ) {
+        // workers stop once the queue is closed and drained
+        let queue = scopeguard::guard(queue, |queue| queue.close());
+        let mut backoff =
+            Backoff::new(self.supervisor.initial_backoff, self.supervisor.max_backoff);
         let mut idx = 0;
+        self.pipeline_status.send_replace(PipelineStatus::Running);
+        loop {
+            let last_idx = idx;
+            let err = match self.get_data_channel(&queue, &sender, &mut idx).await {
+                Ok(()) => break,
+                Err(err) => err,
+            };
+            // captured something since the last restart, so the previous restart worked
+            if idx > last_idx {
+                backoff.reset();
+            }
+            if err.kind() == ErrorKind::Fatal || backoff.attempt() >= self.supervisor.max_restarts {
+                self.pipeline_status
+                    .send_replace(PipelineStatus::Failed(err.to_string()));
+                return;
+            }
+            loop {
+                self.pipeline_status
+                    .send_replace(PipelineStatus::Restarting {
+                        attempt: backoff.attempt() + 1,
+                        reason: err.to_string(),
+                    });
+                tokio::select! {
+                    _ = time::sleep(backoff.next_delay()) => {}
+                    _ = self.stopped() => return,
+                }
+                match self.restart_capturer().await {
+                    Ok(()) => break,
+                    Err(restart_err)
+                        if restart_err.kind() == ErrorKind::Transient
+                            && backoff.attempt() < self.supervisor.max_restarts => {}
+                    Err(restart_err) => {
+                        self.pipeline_status
+                            .send_replace(PipelineStatus::Failed(restart_err.to_string()));
+                        return;
+                    }
+                }
+            }
+            self.pipeline_status.send_replace(PipelineStatus::Running);
+        }
+    }
+
+    // find the game window again and crop into the loot area, like start does
+    async fn restart_capturer(&self) -> Result<(), error::Error> {
+        let screen = game_screen()?;
+        let full_window = capturer::config(0, 0, screen.width, screen.height, 1.0)?;
+        {
+            let mut capturer = self.capturer.as_ref().unwrap().lock().await;
+            capturer.stop_capture();
+            *capturer = full_window;
+        }
+        self.recapture_into_exact_frame(screen).await?;
+        self.capturer.as_ref().unwrap().lock().await.start_capture();
+        Ok(())
+    }
+
+    // resolves once `stop` is called
+    async fn stopped(&self) {
+        _ = self
+            .stop_signal
+            .subscribe()
+            .wait_for(|stopped| *stopped)
+            .await;
+    }
+
+    // capture frames into the queue, ocr itself is done by the workers
+    async fn get_data_channel(
+        &self,
+        queue: &FrameQueue<FrameJob>,
+        sender: &mpsc::Sender<OcrChannel>,
+        idx: &mut u64,
+    ) -> Result<(), error::Error> {
+        let mut paused = false;
         loop {
-            {
-                let status = self.status.lock().await;
-                match *status {
-                    CoreStatus::Stopped => break,
-                    _ => {}
-                };
+            match self.status().await {
+                CoreStatus::Stopped => break,
+                CoreStatus::Paused => {
+                    if !paused {
+                        self.capturer.as_ref().unwrap().lock().await.stop_capture();
+                        paused = true;
+                    }
+                    time::sleep(PAUSE_POLL_INTERVAL).await;
+                    continue;
+                }
+                _ => {}
+            }
+            if paused {
+                self.capturer.as_ref().unwrap().lock().await.start_capture();
+                paused = false;
             }
 
-            let frame = {
-                // println!("geting capturer");
-                let mut capturer = self.capturer.as_ref().unwrap().lock().await;
-                let frame = capturer.get_next_frame().await;
-                // println!("frame fetched");
-                frame
+            // the capturer is locked while waiting for the frame, which may not come for a while,
+            // e.g. the game is minimized. stop drops the wait and the lock with it
+            let frame = tokio::select! {
+                frame = async {
+                    self.capturer.as_ref().unwrap().lock().await.get_next_frame().await
+                } => frame,
+                _ = self.stopped() => break,
             };
 
             if let Err(frame) = frame {
                 return Err(error::Error::CapturerError(frame.to_string()));
             }
             let frame = frame.unwrap().to_rgb();
-            let ocr_client = self.ocr_client.clone();
-            let cloned_sender = sender.clone();
-            // _ = image::save_buffer(
-            //     format!("{}.png", chrono::Local::now().timestamp()),
-            //     &frame.data,
-            //     frame.width,
-            //     frame.height,
-            //     image::ExtendedColorType::Rgb8,
-            // );
-            tokio::spawn(async move {
-                let result = ocr_client
-                    .do_ocr(OcrInput {
-                        data: frame.data.clone(),
-                        width: frame.width,
-                        height: frame.height,
-                    })
-                    .await
-                    .map_err(|e| error::Error::OcrError(e.to_string()));
-                if result.is_err() {
-                    // return result;
-                    _ = cloned_sender
-                        .send(OcrChannel {
-                            index: idx,
-                            result: None,
-                            err: result.err(),
-                        })
-                        .await;
-                    return;
-                }
-
-                _ = cloned_sender
-                    .send(OcrChannel {
-                        index: idx,
-                        result: Some(result.unwrap()),
-                        err: None,
-                    })
-                    .await;
+            let dropped = queue.push(FrameJob {
+                index: *idx,
+                captured_at: chrono::Local::now().timestamp_millis(),
+                frame,
             });
-            idx += 1;
+            if let Some(dropped) = dropped {
+                // tell the reorder buffer right away instead of waiting for the timeout
+                _ = sender.try_send(OcrChannel {
+                    index: dropped.index,
+                    captured_at: dropped.captured_at,
+                    result: None,
+                    err: None,
+                });
+            }
+            *idx += 1;
         }
-
-        // let mut img = RgbImage::from_raw(frame.width, frame.height, frame.data).unwrap();
-        // for v in output.data.iter() {
-        //     let rect =
-        //         Rect::at(v.area.x as i32, v.area.y as i32).of_size(v.area.width, v.area.height);
-        //     draw_hollow_rect_mut(&mut img, rect, Rgb([0, 255, 0]));
-        // }
-        // img.save(format!("{}.png", chrono::Local::now().timestamp_millis()));
         return Ok(());
     }
 
-    async fn recapture_into_exact_frame(&self) -> Result<(), error::Error> {
+    async fn recapture_into_exact_frame(&self, screen: GameScreen) -> Result<(), error::Error> {
         self.capturer.as_ref().unwrap().lock().await.start_capture();
         let data = self.get_data().await?;
-        self.crop(data).await
+        self.crop(data, screen).await
     }
 
-    async fn crop(&self, input: ocr::OcrOutput) -> Result<(), error::Error> {
+    async fn crop(&self, input: ocr::OcrOutput, screen: GameScreen) -> Result<(), error::Error> {
         let config = {
-            let tracker = self.loot_tracker.lock().await;
+            let detection_mode = self.loot_tracker.detection_mode().await;
             let config = BlackDesertLootTracker::screen_config(
-                tracker.detection_mode,
+                detection_mode,
                 input.data.into_iter().map(Into::into).collect(),
                 Some(Screen {
-                    height: self.game_screen.height,
-                    width: self.game_screen.width,
-                    scale: self.game_screen.scale,
+                    height: screen.height,
+                    width: screen.width,
+                    scale: screen.scale,
                 }),
             );
             config
@@ -348,7 +606,7 @@ impl Core {
         {
             let mut capturer = self.capturer.as_ref().unwrap().lock().await;
             capturer.stop_capture();
-            let new_capturer = capturer::live_capture(config).unwrap();
+            let new_capturer = capturer::live_capture(config)?;
             *capturer = new_capturer
         }
         // println!("crop done");
@@ -358,6 +616,16 @@ impl Core {
     pub fn use_capturer(&mut self, capturer: scap::capturer::Capturer) {
         self.capturer = Some(Arc::new(Mutex::new(capturer)));
     }
+    pub fn use_pipeline(&mut self, config: PipelineConfig) {
+        self.pipeline = config;
+    }
+    pub fn use_supervisor(&mut self, config: SupervisorConfig) {
+        self.supervisor = config;
+    }
+    /// Returns a receiver that will get updates whenever the capture pipeline status changes
+    pub fn get_pipeline_status(&self) -> watch::Receiver<PipelineStatus> {
+        self.pipeline_status.subscribe()
+    }
     /// Returns a receiver that will get updates whenever loot data changes
     pub fn get_loot_updates(&self) -> watch::Receiver<HashMap<String, LootData>> {
         self.loot_sender.subscribe()
@@ -365,7 +633,7 @@ impl Core {
 
     /// Gets the current loot data snapshot
     pub async fn get_current_loot(&self) -> HashMap<String, LootData> {
-        self.loot_tracker.lock().await.get_loot_data().clone()
+        self.loot_tracker.loot().await
     }
 }
 
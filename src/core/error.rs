@@ -3,10 +3,35 @@ use thiserror::Error;
 pub enum Error {
     #[error("Capturer Error: {0}")]
     CapturerError(String),
+    #[error("Game window not found")]
+    TargetNotFound,
     #[error("OCR Error: {0}")]
     OcrError(String),
+    #[error("Invalid State: {0}")]
+    InvalidState(String),
     #[error("Image Error: {0}")]
     ImageError(String),
     #[error("Error: {0}")]
     UnknownError(String),
 }
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+    // worth retrying, the game or the ocr server may come back
+    Transient,
+    // retrying won't help
+    Fatal,
+}
+
+impl Error {
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Error::CapturerError(_) | Error::TargetNotFound | Error::OcrError(_) => {
+                ErrorKind::Transient
+            }
+            Error::InvalidState(_) | Error::ImageError(_) | Error::UnknownError(_) => {
+                ErrorKind::Fatal
+            }
+        }
+    }
+}
@@ -1,6 +1,10 @@
 mod capturer;
 mod core;
 mod error;
+mod pipeline;
+mod supervisor;
 pub use capturer::*;
 pub use core::*;
 pub use error::*;
+pub use pipeline::*;
+pub use supervisor::*;
@@ -0,0 +1,246 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use tokio::sync::Notify;
+
+#[derive(Clone, Copy, Debug)]
+pub struct PipelineConfig {
+    // amount of ocr request running at the same time
+    pub workers: usize,
+    // frames waiting for a free worker, the oldest one is dropped when full
+    pub queue_capacity: usize,
+    // how long a missing index can hold back the newer results before it is skipped
+    pub reorder_timeout: Duration,
+}
+
+impl Default for PipelineConfig {
+    fn default() -> Self {
+        Self {
+            workers: 2,
+            queue_capacity: 4,
+            reorder_timeout: Duration::from_secs(5),
+        }
+    }
+}
+
+/// Bounded queue between the capturer and the ocr workers.
+/// pushing never waits, when the queue is full the oldest frame is dropped and returned
+/// because a fresh frame is always worth more than a stale one.
+pub struct FrameQueue<T> {
+    inner: Mutex<FrameQueueInner<T>>,
+    notify: Notify,
+    capacity: usize,
+}
+
+struct FrameQueueInner<T> {
+    items: VecDeque<T>,
+    closed: bool,
+}
+
+impl<T> FrameQueue<T> {
+    pub fn new(capacity: usize) -> Self {
+        let capacity = capacity.max(1);
+        Self {
+            inner: Mutex::new(FrameQueueInner {
+                items: VecDeque::with_capacity(capacity),
+                closed: false,
+            }),
+            notify: Notify::new(),
+            capacity,
+        }
+    }
+
+    // returns the dropped item if any. pushing into closed queue returns the item back
+    pub fn push(&self, item: T) -> Option<T> {
+        let dropped = {
+            let mut inner = self.inner.lock().unwrap();
+            if inner.closed {
+                return Some(item);
+            }
+            let dropped = if inner.items.len() >= self.capacity {
+                inner.items.pop_front()
+            } else {
+                None
+            };
+            inner.items.push_back(item);
+            dropped
+        };
+        self.notify.notify_one();
+        dropped
+    }
+
+    // wait for the next item. None means the queue is closed and empty
+    pub async fn pop(&self) -> Option<T> {
+        loop {
+            let notified = self.notify.notified();
+            {
+                let mut inner = self.inner.lock().unwrap();
+                if let Some(item) = inner.items.pop_front() {
+                    return Some(item);
+                }
+                if inner.closed {
+                    return None;
+                }
+            }
+            notified.await;
+        }
+    }
+
+    pub fn close(&self) {
+        self.inner.lock().unwrap().closed = true;
+        self.notify.notify_waiters();
+    }
+
+    pub fn len(&self) -> usize {
+        self.inner.lock().unwrap().items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Puts the ocr results back into capture order.
+/// when an index is missing for longer than the timeout it is skipped,
+/// so one lost response can't hold back every result behind it.
+pub struct ReorderBuffer<T> {
+    next: u64,
+    pending: HashMap<u64, T>,
+    timeout: Duration,
+    // since when we are waiting for `next`
+    waiting_since: Option<Instant>,
+}
+
+impl<T> ReorderBuffer<T> {
+    pub fn new(timeout: Duration) -> Self {
+        Self {
+            next: 0,
+            pending: HashMap::new(),
+            timeout,
+            waiting_since: None,
+        }
+    }
+
+    pub fn insert(&mut self, index: u64, item: T) {
+        // already skipped, too late to be processed
+        if index < self.next {
+            return;
+        }
+        self.pending.insert(index, item);
+    }
+
+    // take every item that is ready in order
+    pub fn drain(&mut self, now: Instant) -> Vec<T> {
+        let mut out = Vec::new();
+        loop {
+            if let Some(item) = self.pending.remove(&self.next) {
+                out.push(item);
+                self.next += 1;
+                self.waiting_since = None;
+                continue;
+            }
+            if self.pending.is_empty() {
+                self.waiting_since = None;
+                break;
+            }
+            match self.waiting_since {
+                Some(since) if now.duration_since(since) >= self.timeout => {
+                    // give up on the missing index and jump to the oldest result we have
+                    self.next = *self.pending.keys().min().unwrap();
+                    self.waiting_since = None;
+                }
+                Some(_) => break,
+                None => {
+                    self.waiting_since = Some(now);
+                    break;
+                }
+            }
+        }
+        out
+    }
+
+    // take everything left in index order, used on shutdown when no more result will come
+    pub fn flush(&mut self) -> Vec<T> {
+        let mut indexes: Vec<u64> = self.pending.keys().copied().collect();
+        indexes.sort_unstable();
+        let out: Vec<T> = indexes
+            .iter()
+            .filter_map(|idx| self.pending.remove(idx))
+            .collect();
+        if let Some(last) = indexes.last() {
+            self.next = last + 1;
+        }
+        self.waiting_since = None;
+        out
+    }
+
+    pub fn next_index(&self) -> u64 {
+        self.next
+    }
+
+    pub fn pending_len(&self) -> usize {
+        self.pending.len()
+    }
+}
+
+#[cfg(test)]
+mod test_pipeline {
+    use std::time::{Duration, Instant};
+
+    use crate::core::pipeline::{FrameQueue, ReorderBuffer};
+
+    #[test]
+    fn queue_drop_oldest() {
+        let queue = FrameQueue::new(2);
+        assert_eq!(queue.push(1), None);
+        assert_eq!(queue.push(2), None);
+        assert_eq!(queue.push(3), Some(1));
+        assert_eq!(queue.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn queue_close() {
+        let queue = FrameQueue::new(2);
+        queue.push(1);
+        queue.close();
+        assert_eq!(queue.push(2), Some(2));
+        assert_eq!(queue.pop().await, Some(1));
+        assert_eq!(queue.pop().await, None);
+    }
+
+    #[test]
+    fn reorder_in_order() {
+        let mut buffer = ReorderBuffer::new(Duration::from_secs(5));
+        let now = Instant::now();
+        buffer.insert(1, "b");
+        assert!(buffer.drain(now).is_empty());
+        buffer.insert(0, "a");
+        assert_eq!(buffer.drain(now), vec!["a", "b"]);
+        assert_eq!(buffer.next_index(), 2);
+    }
+
+    #[test]
+    fn reorder_skip_missing_after_timeout() {
+        let mut buffer = ReorderBuffer::new(Duration::from_secs(5));
+        let now = Instant::now();
+        buffer.insert(1, "b");
+        buffer.insert(2, "c");
+        assert!(buffer.drain(now).is_empty());
+        assert!(buffer.drain(now + Duration::from_secs(4)).is_empty());
+        assert_eq!(buffer.drain(now + Duration::from_secs(5)), vec!["b", "c"]);
+        // late result of the skipped index is ignored
+        buffer.insert(0, "a");
+        assert!(buffer.drain(now + Duration::from_secs(6)).is_empty());
+        assert_eq!(buffer.pending_len(), 0);
+    }
+
+    #[test]
+    fn reorder_flush() {
+        let mut buffer = ReorderBuffer::new(Duration::from_secs(5));
+        buffer.insert(3, "d");
+        buffer.insert(1, "b");
+        assert_eq!(buffer.flush(), vec!["b", "d"]);
+        assert_eq!(buffer.next_index(), 4);
+    }
+}
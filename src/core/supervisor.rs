@@ -0,0 +1,175 @@
+use core::fmt;
+use std::time::Duration;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PipelineStatus {
+    Idle,
+    Running,
+    // capturer failed and will be rebuilt after the backoff
+    Restarting {
+        attempt: u32,
+        reason: String,
+    },
+    // too many ocr request failed in a row, frames are still captured
+    OcrDown {
+        consecutive_failures: u32,
+        reason: String,
+    },
+    // gave up, needs to be started again
+    Failed(String),
+    Stopped,
+}
+
+impl fmt::Display for PipelineStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PipelineStatus::Idle => write!(f, "idle"),
+            PipelineStatus::Running => write!(f, "running"),
+            PipelineStatus::Restarting { attempt, reason } => {
+                write!(f, "restarting capturer (attempt {}): {}", attempt, reason)
+            }
+            PipelineStatus::OcrDown {
+                consecutive_failures,
+                reason,
+            } => write!(
+                f,
+                "ocr backend down ({} failures in a row): {}",
+                consecutive_failures, reason
+            ),
+            PipelineStatus::Failed(reason) => write!(f, "failed: {}", reason),
+            PipelineStatus::Stopped => write!(f, "stopped"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct SupervisorConfig {
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+    // consecutive restart without a single captured frame before giving up
+    pub max_restarts: u32,
+    // consecutive ocr failure before the backend is declared down
+    pub ocr_failure_threshold: u32,
+}
+
+impl Default for SupervisorConfig {
+    fn default() -> Self {
+        Self {
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(30),
+            max_restarts: 10,
+            ocr_failure_threshold: 5,
+        }
+    }
+}
+
+pub struct Backoff {
+    initial: Duration,
+    max: Duration,
+    attempt: u32,
+}
+
+impl Backoff {
+    pub fn new(initial: Duration, max: Duration) -> Self {
+        Self {
+            initial,
+            max,
+            attempt: 0,
+        }
+    }
+
+    // delay before the next attempt, doubled every call
+    pub fn next_delay(&mut self) -> Duration {
+        let factor = 2u32.saturating_pow(self.attempt.min(16));
+        self.attempt += 1;
+        self.initial.saturating_mul(factor).min(self.max)
+    }
+
+    pub fn attempt(&self) -> u32 {
+        self.attempt
+    }
+
+    pub fn reset(&mut self) {
+        self.attempt = 0;
+    }
+}
+
+pub struct OcrHealth {
+    threshold: u32,
+    consecutive_failures: u32,
+}
+
+impl OcrHealth {
+    pub fn new(threshold: u32) -> Self {
+        Self {
+            threshold: threshold.max(1),
+            consecutive_failures: 0,
+        }
+    }
+
+    // returns true when this failure is the one that marks the backend down
+    pub fn record_failure(&mut self) -> bool {
+        self.consecutive_failures += 1;
+        self.consecutive_failures == self.threshold
+    }
+
+    // returns true when the backend was down and is back
+    pub fn record_success(&mut self) -> bool {
+        let was_down = self.is_down();
+        self.consecutive_failures = 0;
+        was_down
+    }
+
+    pub fn is_down(&self) -> bool {
+        self.consecutive_failures >= self.threshold
+    }
+
+    pub fn consecutive_failures(&self) -> u32 {
+        self.consecutive_failures
+    }
+}
+
+#[cfg(test)]
+mod test_supervisor {
+    use std::time::Duration;
+
+    use crate::core::{Backoff, Error, ErrorKind, OcrHealth};
+
+    #[test]
+    fn backoff_doubles_until_max() {
+        let mut backoff = Backoff::new(Duration::from_millis(500), Duration::from_secs(3));
+        assert_eq!(backoff.next_delay(), Duration::from_millis(500));
+        assert_eq!(backoff.next_delay(), Duration::from_secs(1));
+        assert_eq!(backoff.next_delay(), Duration::from_secs(2));
+        assert_eq!(backoff.next_delay(), Duration::from_secs(3));
+        assert_eq!(backoff.attempt(), 4);
+        backoff.reset();
+        assert_eq!(backoff.next_delay(), Duration::from_millis(500));
+    }
+
+    #[test]
+    fn ocr_down_after_threshold() {
+        let mut health = OcrHealth::new(3);
+        assert!(!health.record_failure());
+        assert!(!health.record_failure());
+        assert!(health.record_failure());
+        // only reported once
+        assert!(!health.record_failure());
+        assert!(health.is_down());
+        assert!(health.record_success());
+        assert!(!health.is_down());
+    }
+
+    #[test]
+    fn error_kind() {
+        assert_eq!(Error::TargetNotFound.kind(), ErrorKind::Transient);
+        assert_eq!(
+            Error::OcrError("timeout".to_string()).kind(),
+            ErrorKind::Transient
+        );
+        assert_eq!(
+            Error::ImageError("bad frame".to_string()).kind(),
+            ErrorKind::Fatal
+        );
+    }
+}
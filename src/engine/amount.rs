@@ -0,0 +1,211 @@
+use serde::{Deserialize, Serialize};
+
+use crate::engine::Language;
+
+// separators ocr may put into a number no matter the language
+const GROUP_SEPARATORS: [char; 3] = [',', '.', '\''];
+// stray punctuation around an amount. not `|` or `!`, those are misread 1s
+const STRAY: [char; 8] = [',', '.', '\'', '"', '(', ')', '[', ']'];
+
+/// How numbers are written in the game client's language, any of `, . '` and spaces
+/// group thousands, the decimal separator only matters for "1.2K".
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NumberFormat {
+    pub decimal: char,
+}
+
+impl Default for NumberFormat {
+    fn default() -> Self {
+        Self { decimal: '.' }
+    }
+}
+
+impl NumberFormat {
+    pub fn for_language(language: Language) -> Self {
+        match language {
+            Language::De
+            | Language::Es
+            | Language::Pt
+            | Language::Tr
+            | Language::Id
+            | Language::Fr
+            | Language::Ru => Self { decimal: ',' },
+            Language::En | Language::Kr | Language::Jp | Language::Tw | Language::Th => {
+                Self::default()
+            }
+        }
+    }
+}
+
+fn multiplier(suffix: char) -> Option<u64> {
+    match suffix.to_ascii_lowercase() {
+        'k' => Some(1_000),
+        'm' => Some(1_000_000),
+        'b' => Some(1_000_000_000),
+        _ => None,
+    }
+}
+
+/// Reads "1,234,567", "1.234.567", "1 234" or "1.2K" as an amount.
+/// None when it isn't a number, the grouping is off or it doesn't fit in a u64.
+/// A suffix needs decimals, a lone "1B" is more likely a misread 18.
+pub fn parse_amount(s: &str, format: &NumberFormat) -> Option<u64> {
+    parse(s, format, false)
+}
+
+/// Like `parse_amount`, but "3M" is read too since the chat shortens silver amounts.
+pub fn parse_silver(s: &str, format: &NumberFormat) -> Option<u64> {
+    parse(s, format, true)
+}
+
+fn parse(s: &str, format: &NumberFormat, any_suffix: bool) -> Option<u64> {
+    // spaces are group separators or noise, either way not part of the value
+    let s: String = s
+        .trim_matches(|c: char| c.is_whitespace() || STRAY.contains(&c))
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .collect();
+    let last = s.chars().last()?;
+    let (number, multiplier) = match multiplier(last) {
+        Some(multiplier) => (&s[..s.len() - last.len_utf8()], multiplier),
+        None => (s.as_str(), 1),
+    };
+    if number.is_empty()
+        || !number
+            .chars()
+            .all(|c| c.is_ascii_digit() || GROUP_SEPARATORS.contains(&c))
+    {
+        return None;
+    }
+    let (integer, fraction) = split_decimal(number, multiplier, format);
+    if multiplier > 1 && fraction.is_none() && !any_suffix {
+        return None;
+    }
+    let mut value = grouped(integer)?.checked_mul(multiplier)?;
+    if let Some(fraction) = fraction {
+        if !fraction.chars().all(|c| c.is_ascii_digit()) {
+            return None;
+        }
+        // digits below 1 are cut off, 1.2345K is 1234
+        let mut scale = multiplier;
+        for digit in fraction.chars().filter_map(|c| c.to_digit(10)) {
+            scale /= 10;
+            value = value.checked_add(digit as u64 * scale)?;
+        }
+    }
+    Some(value)
+}
+
+// only amounts with a suffix have decimals. the last separator is the decimal one when it is
+// the language's decimal separator or isn't followed by a group of 3
+fn split_decimal<'a>(
+    number: &'a str,
+    multiplier: u64,
+    format: &NumberFormat,
+) -> (&'a str, Option<&'a str>) {
+    if multiplier == 1 {
+        return (number, None);
+    }
+    let Some(idx) = number.rfind(GROUP_SEPARATORS) else {
+        return (number, None);
+    };
+    let separator = number[idx..].chars().next().unwrap_or_default();
+    let fraction = &number[idx + 1..];
+    if separator == format.decimal || fraction.len() != 3 {
+        (&number[..idx], Some(fraction))
+    } else {
+        (number, None)
+    }
+}
+
+// "1,234,567" with groups of 3 after the first one
+fn grouped(number: &str) -> Option<u64> {
+    let mut groups = number.split(GROUP_SEPARATORS);
+    let first = groups.next()?;
+    if first.is_empty() || (first.len() > 3 && number.contains(GROUP_SEPARATORS)) {
+        return None;
+    }
+    let mut value: u64 = first.parse().ok()?;
+    for group in groups {
+        if group.len() != 3 {
+            return None;
+        }
+        value = value
+            .checked_mul(1_000)?
+            .checked_add(group.parse::<u64>().ok()?)?;
+    }
+    Some(value)
+}
+
+#[cfg(test)]
+mod test_amount {
+    use crate::engine::{Language, NumberFormat, parse_amount, parse_silver};
+
+    #[test]
+    fn english() {
+        let format = NumberFormat::default();
+        let cases: &[(&str, Option<u64>)] = &[
+            ("7", Some(7)),
+            ("1,234,567", Some(1_234_567)),
+            ("1.234.567", Some(1_234_567)),
+            ("1 234 567", Some(1_234_567)),
+            ("1'234", Some(1_234)),
+            ("1.2K", Some(1_200)),
+            ("1.2k", Some(1_200)),
+            ("2.5B", Some(2_500_000_000)),
+            ("1.2345K", Some(1_234)),
+            // no decimals, a misread 18 or 15 rather than a suffix
+            ("1B", None),
+            ("3M", None),
+            ("1,234K", None),
+            // misread 1s are left for the ocr corrector
+            ("|09", None),
+            ("1|", None),
+            // stray ocr punctuation
+            ("2.", Some(2)),
+            ("(12)", Some(12)),
+            (".,109'", Some(109)),
+            ("1,23,456", None),
+            ("12.5", None),
+            ("1,,234", None),
+            ("1234,567", None),
+            ("K", None),
+            ("", None),
+            ("abc", None),
+            ("18446744073709551615", Some(u64::MAX)),
+            ("18446744073709551616", None),
+            ("18446744073709552K", None),
+        ];
+        for (s, expected) in cases {
+            assert_eq!(parse_amount(s, &format), *expected, "{:?}", s);
+        }
+    }
+
+    #[test]
+    fn silver() {
+        let format = NumberFormat::default();
+        let cases: &[(&str, Option<u64>)] = &[
+            ("1,234,567", Some(1_234_567)),
+            ("3M", Some(3_000_000)),
+            ("1B", Some(1_000_000_000)),
+            ("1,234K", Some(1_234_000)),
+            ("1.2K", Some(1_200)),
+            ("18446744073709552K", None),
+        ];
+        for (s, expected) in cases {
+            assert_eq!(parse_silver(s, &format), *expected, "{:?}", s);
+        }
+    }
+
+    #[test]
+    fn decimal_comma() {
+        let german = NumberFormat::for_language(Language::De);
+        assert_eq!(parse_amount("1.234.567", &german), Some(1_234_567));
+        assert_eq!(parse_amount("1,5K", &german), Some(1_500));
+        // a group of 3 after the decimal comma is still a decimal
+        assert_eq!(parse_amount("1,234K", &german), Some(1_234));
+        let french = NumberFormat::for_language(Language::Fr);
+        assert_eq!(parse_amount("1 234 567", &french), Some(1_234_567));
+        assert_eq!(parse_amount("2,5M", &french), Some(2_500_000));
+    }
+}
@@ -14,10 +14,7 @@ use scap::capturer::Resolution;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::{cmp::*, vec};
-use tokio::fs::OpenOptions;
-use tokio::io::AsyncWriteExt;
 use tokio::sync::Mutex;
-use unicode_normalization::UnicodeNormalization;
 #[derive(Clone, Copy)]
 pub enum LootDetectionMode {
     OCRChatLootViaStream,
@@ -32,12 +29,38 @@ pub struct LootData {
     // add hour and minute to improve accuracy
     pub hour: u8,
     pub minute: u8,
+    // unix millis of the drop. the chat line's time on the session's calendar in chat mode,
+    // else the capture time of the frame it was first seen in. None for lines without a time
+    // until they are inserted
+    #[serde(default)]
+    pub timestamp: Option<i64>,
+    #[serde(default)]
+    pub price_status: PriceStatus,
+    // kept apart from `price` so the loot can be valued after tax, see `LootValue`
+    #[serde(default)]
+    pub market_price: Silver,
+    #[serde(default)]
+    pub vendor_price: Silver,
+}
+
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PriceStatus {
+    #[default]
+    Resolved,
+    // lookup still running or waiting for retry, price is 0 until it's done
+    Pending,
+    // every lookup failed, price is 0 until `take_unknown_prices` queues it again
+    Unknown,
 }
 
 impl LootData {
     pub fn calculate(&self) -> Silver {
         self.amount * self.price
     }
+
+    pub fn calculate_with(&self, ctx: &ValuationContext) -> LootValue {
+        LootValue::of(self, ctx)
+    }
 }
 
 struct LootDatas(Vec<LootData>);
@@ -45,6 +68,15 @@ struct LootDatas(Vec<LootData>);
 use std::ops::Deref;
 
 use crate::engine::item_fetcher::{self, ItemFetcher};
+use crate::engine::{
+    CachedFetcher, ChainFetcher, ChatClock, ChatEvent, ChatMessage, ChatParser, ConfusionMatrix,
+    DEFAULT_CACHE_PATH, Disambiguator, EXPENSES_PATH, Expense, ExpenseConfig, Fetcher,
+    GRIND_SPOTS_PATH, GrindSpot, GrindSpots, ITEM_CHOICES_PATH, ITEM_DB_PATH, ItemDb,
+    ItemDbFetcher, LOCALES_PATH, Language, LocalePacks, LootValue, NameIndex, OCR_CONFUSIONS_PATH,
+    OcrCorrector, OverrideFetcher, PRICE_OVERRIDES_PATH, PRICE_RULES_PATH, PriceAction,
+    PriceResolverConfig, PriceRules, Region, STATIC_PRICES_PATH, Session, SpotChange, SpotDetector,
+    SpotFilter, StaticPriceFetcher, ValuationContext,
+};
 
 impl Deref for LootDatas {
     type Target = Vec<LootData>;
@@ -54,6 +86,14 @@ impl Deref for LootDatas {
     }
 }
 
+// the same drop at the same minute of another day is another drop
+fn same_time(old: &LootData, new: &LootData) -> bool {
+    match (old.timestamp, new.timestamp) {
+        (Some(old), Some(new)) => old == new,
+        _ => old.hour == new.hour && old.minute == new.minute,
+    }
+}
+
 impl LootDatas {
     fn diff(old: &[LootData], new: &[LootData]) -> Vec<LootData> {
         let mut old_index = 0;
@@ -87,8 +127,7 @@ impl LootDatas {
             if new_item.name == old_item.name
                 && (old_item.amount == new_item.amount
                     || is_ocr_misread(&old_item.amount.to_string(), &new_item.amount.to_string()))
-                && old_item.hour == new_item.hour
-                && old_item.minute == new_item.minute
+                && same_time(old_item, new_item)
             {
                 old_index += 1;
                 new_index += 1;
@@ -200,6 +239,23 @@ mod test_diff {
         assert_eq!(diff[0].amount, 92);
     }
     #[test]
+    fn same_minute_other_day() {
+        let drop = |timestamp| LootData {
+            name: "Black Stone".to_string(),
+            amount: 1,
+            hour: 16,
+            minute: 8,
+            timestamp,
+            ..Default::default()
+        };
+        let day = 24 * 60 * 60 * 1000;
+        let old = vec![drop(Some(0))];
+        assert!(LootDatas::diff(&old, &[drop(Some(0))]).is_empty());
+        assert_eq!(LootDatas::diff(&old, &[drop(Some(day))]).len(), 1);
+        // without a date only the time of day is compared
+        assert!(LootDatas::diff(&old, &[drop(None)]).is_empty());
+    }
+    #[test]
     fn test_diff_input() {
         let file_reader = File::open("1753492193_old.json").unwrap();
         let old_data: Vec<LootData> = serde_json::from_reader(file_reader).unwrap();
@@ -211,28 +267,452 @@ mod test_diff {
     }
 }
 
+#[cfg(test)]
+mod test_price_pending {
+    use crate::engine::{BlackDesertLootTracker, Item, LootData, PriceStatus, Silver};
+
+    fn pending(name: &str, amount: u64) -> LootData {
+        LootData {
+            name: name.to_string(),
+            amount,
+            price_status: PriceStatus::Pending,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn resolve_into_canonical_name() {
+        let mut tracker = BlackDesertLootTracker::new();
+        tracker
+            .loot_table
+            .insert("Black stone".to_string(), pending("Black stone", 3));
+        tracker.resolve_price(
+            "Black stone",
+            Some(Item {
+                id: 16001,
+                name: "Black Stone".to_string(),
+                price: Silver::new(200),
+                ..Default::default()
+            }),
+        );
+        let entry = tracker.get_loot_data().get("Black Stone").unwrap();
+        assert_eq!(entry.id, 16001);
+        assert_eq!(entry.amount, 3);
+        assert_eq!(entry.price_status, PriceStatus::Resolved);
+        assert!(tracker.get_loot_data().get("Black stone").is_none());
+    }
+
+    #[test]
+    fn resolve_merges_existing() {
+        let mut tracker = BlackDesertLootTracker::new();
+        tracker.loot_table.insert(
+            "Silver".to_string(),
+            LootData {
+                name: "Silver".to_string(),
+                amount: 100,
+                ..Default::default()
+            },
+        );
+        tracker
+            .loot_table
+            .insert("silver".to_string(), pending("silver", 20));
+        tracker.resolve_price(
+            "silver",
+            Some(Item {
+                id: 1,
+                name: "Silver".to_string(),
+                price: Silver::new(1),
+                ..Default::default()
+            }),
+        );
+        assert_eq!(tracker.get_loot_data().len(), 1);
+        assert_eq!(tracker.get_loot_data()["Silver"].amount, 120);
+    }
+
+    #[test]
+    fn unresolved_stays_in_table() {
+        let mut tracker = BlackDesertLootTracker::new();
+        tracker
+            .loot_table
+            .insert("Narc Magic Mark".to_string(), pending("Narc Magic Mark", 2));
+        tracker.resolve_price("Narc Magic Mark", None);
+        let entry = &tracker.get_loot_data()["Narc Magic Mark"];
+        assert_eq!(entry.amount, 2);
+        assert_eq!(entry.price_status, PriceStatus::Unknown);
+
+        assert_eq!(tracker.take_unknown_prices(), vec!["Narc Magic Mark"]);
+        assert_eq!(
+            tracker.get_loot_data()["Narc Magic Mark"].price_status,
+            PriceStatus::Pending
+        );
+        assert!(tracker.take_unknown_prices().is_empty());
+    }
+
+    #[tokio::test]
+    async fn insert_queues_new_loot() {
+        let mut tracker = BlackDesertLootTracker::new();
+        let lines = vec!["You have obtained [Black Stone]x7. (16:08)".to_string()];
+        assert_eq!(tracker.insert(&lines, 0).await, 1);
+        // same chat on the next frame
+        assert_eq!(tracker.insert(&lines, 1).await, 0);
+        assert_eq!(tracker.get_loot_data()["Black Stone"].amount, 7);
+        assert_eq!(tracker.take_unpriced(), vec!["Black Stone"]);
+    }
+
+    #[tokio::test]
+    async fn insert_reads_expenses_and_loot() {
+        let mut tracker = BlackDesertLootTracker::new();
+        let lines = vec![
+            "You have spent 12,500 Silver on repairs. (16:07)".to_string(),
+            "You have obtained [Black Stone]x7. (16:08)".to_string(),
+        ];
+        assert_eq!(tracker.insert(&lines, 0).await, 1);
+        assert_eq!(tracker.insert(&lines, 1).await, 0);
+        let spent: Vec<u64> = tracker.expenses().iter().map(|e| e.silver).collect();
+        assert_eq!(spent, [12_500]);
+        assert_eq!(tracker.get_loot_data()["Black Stone"].amount, 7);
+    }
+
+    #[tokio::test]
+    async fn drops_keep_capture_time() {
+        let mut tracker = BlackDesertLootTracker::new();
+        let chat_line = LootData {
+            timestamp: Some(500),
+            ..pending("Black Stone", 1)
+        };
+        tracker
+            .record_drops(vec![pending("Swamp Leaves", 2), chat_line], 1_000)
+            .await;
+        tracker
+            .record_drops(vec![pending("Swamp Leaves", 1)], 2_000)
+            .await;
+        let history: Vec<Option<i64>> = tracker
+            .loot_history
+            .lock()
+            .await
+            .iter()
+            .map(|v| v.timestamp)
+            .collect();
+        assert_eq!(history, [Some(1_000), Some(500), Some(2_000)]);
+        // the table keeps when the loot was first seen
+        assert_eq!(
+            tracker.get_loot_data()["Swamp Leaves"].timestamp,
+            Some(1_000)
+        );
+        assert_eq!(tracker.get_loot_data()["Swamp Leaves"].amount, 3);
+    }
+}
+
+#[cfg(test)]
+mod test_parse_amount {
+    use chrono::{Local, NaiveDate, TimeZone};
+
+    use crate::engine::{
+        BlackDesertLootTracker, ChatClock, ChatParser, Language, LootDetectionMode, OcrCorrector,
+    };
+
+    fn parse(mode: LootDetectionMode, line: &str) -> Option<(String, u64)> {
+        BlackDesertLootTracker::parse_loot(
+            mode,
+            &OcrCorrector::default(),
+            &ChatParser::default(),
+            &mut ChatClock::default(),
+            &line.to_string(),
+        )
+        .map(|loot| (loot.name, loot.amount))
+    }
+
+    #[test]
+    fn drop_log_and_chat() {
+        let drop_log = LootDetectionMode::OCRDropLogViaStream;
+        let chat = LootDetectionMode::OCRChatLootViaStream;
+        let cases = [
+            (drop_log, "Silver x 1,234,567", Some(1_234_567)),
+            (drop_log, "Silver x 1.2K", Some(1_200)),
+            (drop_log, "Silver x 0", None),
+            (
+                chat,
+                "You have obtained [Silver]x1,234,567 (16:08)",
+                Some(1_234_567),
+            ),
+            (chat, "You have obtained [Black Stone]x7. (16:08)", Some(7)),
+            (chat, "You have obtained [Black Stone] (16:08)", Some(1)),
+            (
+                chat,
+                "You have obtained [Silver]x99999999999999999999",
+                None,
+            ),
+        ];
+        for (mode, line, expected) in cases {
+            assert_eq!(
+                parse(mode, line).map(|(_, amount)| amount),
+                expected,
+                "{:?}",
+                line
+            );
+        }
+    }
+
+    #[test]
+    fn client_language() {
+        let mut tracker = BlackDesertLootTracker::new();
+        tracker.set_language(Language::De);
+        assert_eq!(tracker.language(), Language::De);
+        let parse = |tracker: &BlackDesertLootTracker, line: &str| {
+            BlackDesertLootTracker::parse_loot(
+                LootDetectionMode::OCRChatLootViaStream,
+                &tracker.ocr_corrector,
+                &tracker.chat_parser,
+                &mut ChatClock::default(),
+                &line.to_string(),
+            )
+            .map(|loot| (loot.name, loot.amount))
+        };
+        assert_eq!(
+            parse(&tracker, "Ihr habt [Silber]x1,5K erhalten."),
+            Some(("Silber".to_string(), 1_500))
+        );
+        assert_eq!(parse(&tracker, "You have obtained [Silver]x7."), None);
+        tracker.set_language(Language::Kr);
+        assert_eq!(
+            parse(&tracker, "[블랙스톤]x7을(를) 획득했습니다. (16:08)"),
+            Some(("블랙스톤".to_string(), 7))
+        );
+    }
+
+    #[test]
+    fn chat_timestamps() {
+        let start = NaiveDate::from_ymd_opt(2025, 7, 26)
+            .unwrap()
+            .and_hms_opt(23, 50, 0)
+            .unwrap();
+        let mut clock = ChatClock::new(start);
+        let mut parse = |line: &str| {
+            BlackDesertLootTracker::parse_loot(
+                LootDetectionMode::OCRChatLootViaStream,
+                &OcrCorrector::default(),
+                &ChatParser::default(),
+                &mut clock,
+                &line.to_string(),
+            )
+            .unwrap()
+        };
+        let before = parse("You have obtained [Black Stone]x1. (11:59 PM)");
+        let after = parse("You have obtained [Black Stone]x1. (00:01)");
+        assert_eq!((before.hour, before.minute), (23, 59));
+        let midnight = Local
+            .from_local_datetime(
+                &start
+                    .date()
+                    .succ_opt()
+                    .unwrap()
+                    .and_hms_opt(0, 0, 0)
+                    .unwrap(),
+            )
+            .unwrap();
+        assert_eq!(before.timestamp, Some(midnight.timestamp_millis() - 60_000));
+        assert_eq!(after.timestamp, Some(midnight.timestamp_millis() + 60_000));
+        let invalid = parse("You have obtained [Black Stone]x1. (77:99)");
+        assert_eq!(
+            (invalid.hour, invalid.minute, invalid.timestamp),
+            (0, 0, None)
+        );
+    }
+}
+
+#[cfg(test)]
+mod test_canonical_name {
+    use crate::engine::{
+        BlackDesertLootTracker, ExpectedDrop, GrindSpot, GrindSpots, ItemDb, ItemRecord, Language,
+        LootData, NameIndex,
+    };
+
+    #[test]
+    fn catalog_before_loot_table() {
+        let mut tracker = BlackDesertLootTracker::new();
+        tracker.name_index = NameIndex::from_names(["Narc Magic Mark", "Black Stone"]);
+        // a misread already in the table doesn't win over the catalog
+        tracker.loot_table.insert(
+            "Narc Maqic Mark".to_string(),
+            LootData {
+                name: "Narc Maqic Mark".to_string(),
+                ..Default::default()
+            },
+        );
+        assert_eq!(
+            tracker.canonical_name("Narc Maqlc Mark").as_deref(),
+            Some("Narc Magic Mark")
+        );
+        // unknown to the catalog, falls back to the loot table
+        tracker.loot_table.insert(
+            "Swamp Leaves".to_string(),
+            LootData {
+                name: "Swamp Leaves".to_string(),
+                ..Default::default()
+            },
+        );
+        assert_eq!(
+            tracker.canonical_name("Swamp Leaues").as_deref(),
+            Some("Swamp Leaves")
+        );
+    }
+
+    #[test]
+    fn grind_spot_rejects_other_loot() {
+        let mut tracker = BlackDesertLootTracker::new();
+        tracker.name_index = NameIndex::from_names(["Swamp Leaves", "Sausan Insignia"]);
+        tracker.set_grind_spot(Some(GrindSpot {
+            name: "Swamp Fogans".to_string(),
+            trash: vec![ExpectedDrop {
+                name: "Swamp Leaves".to_string(),
+                typical_amount: Some(2),
+                ..Default::default()
+            }],
+            rare: vec![],
+        }));
+        let loot = |name: &str| LootData {
+            name: name.to_string(),
+            amount: 1,
+            ..Default::default()
+        };
+        let mut entries = vec![
+            loot("SWarnp Leaves"),
+            loot("Sausan Insignia"),
+            loot("Silver"),
+            LootData {
+                amount: 200,
+                ..loot("Swamp Leaves")
+            },
+        ];
+        tracker.normalize_loot(&mut entries);
+        let entries = tracker.reject_loot(entries);
+        let names: Vec<&str> = entries.iter().map(|v| v.name.as_str()).collect();
+        assert_eq!(names, vec!["Swamp Leaves", "Silver"]);
+        assert_eq!(tracker.rejected, 2);
+        assert_eq!(tracker.grind_spot().unwrap().name, "Swamp Fogans");
+
+        tracker.set_grind_spot(None);
+        let mut entries = vec![loot("Sausan Insignia")];
+        tracker.normalize_loot(&mut entries);
+        assert_eq!(tracker.reject_loot(entries).len(), 1);
+        assert_eq!(tracker.rejected, 2);
+    }
+
+    #[tokio::test]
+    async fn localized_chat_at_grind_spot() {
+        let mut tracker = BlackDesertLootTracker::new();
+        let item = |id: u64, en: &str, de: &str| ItemRecord {
+            id,
+            names: [("en", en), ("de", de)]
+                .into_iter()
+                .map(|(lang, name)| (lang.to_string(), name.to_string()))
+                .collect(),
+            ..Default::default()
+        };
+        tracker.item_db = ItemDb::new();
+        tracker.item_db.upsert(item(1, "Silver", "Silber"));
+        tracker
+            .item_db
+            .upsert(item(2, "Swamp Leaves", "Sumpfblätter"));
+        tracker
+            .item_db
+            .upsert(item(3, "Sausan Insignia", "Sausan-Abzeichen"));
+        tracker.set_language(Language::De);
+        tracker.grind_spots.common = vec!["Silver".to_string()];
+        tracker.set_grind_spot(Some(GrindSpot {
+            name: "Swamp Fogans".to_string(),
+            trash: vec![ExpectedDrop {
+                name: "Swamp Leaves".to_string(),
+                ..Default::default()
+            }],
+            rare: vec![],
+        }));
+        let lines: Vec<String> = [
+            "Ihr habt [Sumpfblätter]x3 erhalten. (16:08)",
+            "Ihr habt [Silber]x1,5K erhalten. (16:08)",
+            "Ihr habt [Sausan-Abzeichen]x1 erhalten. (16:09)",
+        ]
+        .into_iter()
+        .map(str::to_string)
+        .collect();
+        assert_eq!(tracker.insert(&lines, 0).await, 2);
+        let loot = tracker.get_loot_data();
+        assert_eq!(
+            (loot["Swamp Leaves"].id, loot["Swamp Leaves"].amount),
+            (2, 3)
+        );
+        assert_eq!((loot["Silver"].id, loot["Silver"].amount), (1, 1_500));
+        assert_eq!(loot.len(), 2);
+        // the rejected line is counted once while it stays in the chat
+        assert_eq!(tracker.insert(&lines, 1).await, 0);
+        assert_eq!(tracker.session().await.rejected, 1);
+    }
+
+    #[tokio::test]
+    async fn detected_spot_labels_session() {
+        let mut tracker = BlackDesertLootTracker::new();
+        tracker.grind_spots = serde_json::from_str(
+            r#"{ "spots": [
+                { "name": "Swamp Fogans", "trash": [{ "name": "Swamp Leaves" }] },
+                { "name": "Sausans", "trash": [{ "name": "Sausan Insignia" }] }
+            ] }"#,
+        )
+        .unwrap();
+        let drops = |name: &str, n: usize| -> Vec<LootData> {
+            (0..n)
+                .map(|_| LootData {
+                    name: name.to_string(),
+                    amount: 1,
+                    ..Default::default()
+                })
+                .collect()
+        };
+        tracker.detect_spot(&drops("Swamp Leaves", 5));
+        let session = tracker.session().await;
+        assert_eq!(session.grind_spot.as_deref(), Some("Swamp Fogans"));
+        assert_eq!(session.spot_changes.len(), 1);
+        // detected spots never reject loot
+        assert!(tracker.grind_spot().is_none());
+
+        // a selected spot wins and stops the detection
+        let sausans = tracker.grind_spots.find("Sausans").cloned();
+        tracker.set_grind_spot(sausans);
+        tracker.detect_spot(&drops("Swamp Leaves", 30));
+        let session = tracker.session().await;
+        assert_eq!(session.grind_spot.as_deref(), Some("Sausans"));
+        assert_eq!(session.spot_changes.len(), 2);
+
+        tracker.use_spot_detection();
+        assert!(tracker.spot_label().is_none());
+        tracker.grind_spots = GrindSpots::default();
+        tracker.detect_spot(&drops("Swamp Leaves", 30));
+        assert!(tracker.spot_label().is_none());
+    }
+}
+
 #[derive(Copy, Clone, Default, Debug, Serialize, Deserialize)]
 pub struct Silver(u64);
 
 // Allow Silver * Silver
+// silver math saturates, an absurd amount shows as the max instead of panicking or wrapping
 impl Mul for Silver {
     type Output = Silver;
 
     fn mul(self, rhs: Silver) -> Silver {
-        Silver(self.0 * rhs.0)
+        Silver(self.0.saturating_mul(rhs.0))
     }
 }
 
 impl Add for Silver {
     type Output = Silver;
     fn add(self, rhs: Silver) -> Silver {
-        Silver(self.0 + rhs.0)
+        Silver(self.0.saturating_add(rhs.0))
     }
 }
 
 impl AddAssign for Silver {
     fn add_assign(&mut self, rhs: Self) {
-        self.0 += rhs.0;
+        self.0 = self.0.saturating_add(rhs.0);
     }
 }
 // Allow u64 * Silver
@@ -240,7 +720,7 @@ impl Mul<Silver> for u64 {
     type Output = Silver;
 
     fn mul(self, rhs: Silver) -> Silver {
-        Silver(self * rhs.0)
+        Silver(self.saturating_mul(rhs.0))
     }
 }
 
@@ -261,6 +741,13 @@ impl Silver {
     pub fn new(data: u64) -> Self {
         Silver(data)
     }
+    pub fn value(&self) -> u64 {
+        self.0
+    }
+    // multiply by a rate like the market tax, rounded down
+    pub fn scale(self, rate: f64) -> Silver {
+        Silver((self.0 as f64 * rate.max(0.0)).floor() as u64)
+    }
 }
 
 impl fmt::Display for Silver {
@@ -279,6 +766,16 @@ mod test_silver {
         a += b;
         assert_eq!(a.0, 3);
     }
+
+    #[test]
+    fn silver_saturates() {
+        let mut a = Silver::new(u64::MAX);
+        a += Silver::new(1);
+        assert_eq!(a.0, u64::MAX);
+        assert_eq!((a + Silver::new(1)).0, u64::MAX);
+        assert_eq!((2 * a).0, u64::MAX);
+        assert_eq!((a * Silver::new(2)).0, u64::MAX);
+    }
 }
 /* todo:
 1. add ocr function to struct
@@ -293,7 +790,41 @@ pub struct BlackDesertLootTracker {
     // pub stream_config: OCRViaStreamConfig,
     mutex: Mutex<u8>,
     state: State,
-    pub item_fetcher: item_fetcher::Fetcher,
+    pub item_fetcher: Arc<item_fetcher::Fetcher>,
+    pub price_resolver: PriceResolverConfig,
+    pub price_rules: Arc<PriceRules>,
+    // local item catalog, turns names read in the client's language into english ones
+    pub item_db: ItemDb,
+    // every known item name in the client's language, ocr output is matched against it before anything else
+    pub name_index: NameIndex,
+    // fixes misread characters before the names are matched
+    pub ocr_corrector: OcrCorrector,
+    pub chat_parser: ChatParser,
+    // dates the chat times, anchored to the session start
+    chat_clock: ChatClock,
+    // chat messages and number formats per client language
+    pub locales: LocalePacks,
+    language: Language,
+    pub expense_config: ExpenseConfig,
+    // silver spent this session, only seen in chat mode
+    expenses: Vec<Expense>,
+    // the chat lines still on screen, as `loot_entry_tracker` for loot
+    expense_entry_tracker: Vec<LootData>,
+    // shared with the fetchers, remembers which item a name means
+    pub disambiguator: Arc<Disambiguator>,
+    pub grind_spots: GrindSpots,
+    // selected spot, None accepts any item
+    grind_spot: Option<SpotFilter>,
+    // guess the spot from the loot while none is selected, only used to label the session
+    pub detect_grind_spot: bool,
+    spot_detector: SpotDetector,
+    spot_changes: Vec<SpotChange>,
+    // drops ignored because of the selected spot, counted once like the drops themselves
+    rejected: u32,
+    // new loot waiting for price lookup
+    unpriced: Vec<String>,
+    // unix millis
+    session_started_at: i64,
 }
 #[derive(Debug, Clone)]
 pub struct OCRViaStreamConfig {
@@ -328,17 +859,158 @@ pub struct AnalyzeCaptureAreaInput {
     pub area: Rect,
 }
 
+// user overrides first, then bdolytics behind the disk cache, then the
+// local item catalog and the static price list when bdolytics is down
+pub fn default_fetcher(
+    region: Region,
+    language: Language,
+    disambiguator: Arc<Disambiguator>,
+) -> item_fetcher::Fetcher {
+    let mut fetcher = item_fetcher::DefaultFetcher::new();
+    fetcher.region = region;
+    fetcher.language = language;
+    fetcher.disambiguator = disambiguator.clone();
+    let mut chain = ChainFetcher::new();
+    match OverrideFetcher::load(PRICE_OVERRIDES_PATH) {
+        Ok(overrides) => chain.push(Fetcher::Overrides(overrides), LOCAL_PROVIDER_TIMEOUT),
+        Err(err) => println!("price overrides ignored: {:#}", err),
+    }
+    chain.push(
+        Fetcher::Cached(CachedFetcher::new(
+            Fetcher::Default(fetcher),
+            // names and prices differ per server and language
+            &format!("{}/{}", region, language),
+            DEFAULT_CACHE_PATH,
+        )),
+        REMOTE_PROVIDER_TIMEOUT,
+    );
+    let item_db = default_item_db();
+    if !item_db.is_empty() {
+        let mut item_db = ItemDbFetcher::new(Arc::new(tokio::sync::RwLock::new(item_db)));
+        item_db.language = language;
+        item_db.disambiguator = disambiguator;
+        chain.push(Fetcher::ItemDb(item_db), LOCAL_PROVIDER_TIMEOUT);
+    }
+    if let Ok(prices) = StaticPriceFetcher::load(STATIC_PRICES_PATH) {
+        chain.push(Fetcher::Static(prices), LOCAL_PROVIDER_TIMEOUT);
+    }
+    Fetcher::Chain(chain)
+}
+
+// the local item catalog, empty without one
+fn default_item_db() -> ItemDb {
+    let path = std::path::Path::new(ITEM_DB_PATH);
+    let mut db = ItemDb::new();
+    let Ok(data) = std::fs::read_to_string(path) else {
+        return db;
+    };
+    let imported = match path.extension().and_then(|e| e.to_str()) {
+        Some("csv") => db.import_csv(&data),
+        _ => db.import_json(&data),
+    };
+    if let Err(err) = imported {
+        println!("item db ignored: {:#}", err);
+    }
+    db
+}
+
+// names from the item catalog in the client's language, only used to match ocr output
+fn default_name_index(db: &ItemDb, language: Language) -> NameIndex {
+    NameIndex::from_names(db.names_in(language.as_str()))
+}
+
+// knows the words of every name in the catalog and the grind spots
+fn default_ocr_corrector(name_index: &NameIndex, grind_spots: &GrindSpots) -> OcrCorrector {
+    let matrix = ConfusionMatrix::load(OCR_CONFUSIONS_PATH).unwrap_or_else(|err| {
+        println!("ocr confusions ignored: {:#}", err);
+        ConfusionMatrix::default()
+    });
+    let spot_drops = grind_spots
+        .spots
+        .iter()
+        .flat_map(|spot| spot.drops())
+        .map(|drop| drop.name.as_str());
+    OcrCorrector::new(matrix)
+        .with_words(name_index.names())
+        .with_words(spot_drops)
+        .with_words(grind_spots.common.iter().map(String::as_str))
+}
+
+fn default_locales() -> LocalePacks {
+    LocalePacks::load(LOCALES_PATH).unwrap_or_else(|err| {
+        println!("locale packs ignored: {:#}", err);
+        LocalePacks::default()
+    })
+}
+
+fn default_expense_config() -> ExpenseConfig {
+    ExpenseConfig::load(EXPENSES_PATH).unwrap_or_else(|err| {
+        println!("expense config ignored: {:#}", err);
+        ExpenseConfig::default()
+    })
+}
+
+fn default_grind_spots() -> GrindSpots {
+    GrindSpots::load(GRIND_SPOTS_PATH).unwrap_or_else(|err| {
+        println!("grind spots ignored: {:#}", err);
+        GrindSpots::default()
+    })
+}
+
+fn default_price_rules() -> PriceRules {
+    PriceRules::load(PRICE_RULES_PATH).unwrap_or_else(|err| {
+        println!("price rules ignored: {:#}", err);
+        PriceRules::default()
+    })
+}
+
+const LOCAL_PROVIDER_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(1);
+// bdolytics does up to 3 request per item
+const REMOTE_PROVIDER_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(20);
+
 impl BlackDesertLootTracker {
     pub fn new() -> Self {
-        Self {
+        let disambiguator = Arc::new(Disambiguator::load(ITEM_CHOICES_PATH));
+        let item_db = default_item_db();
+        let name_index = default_name_index(&item_db, Language::default());
+        let grind_spots = default_grind_spots();
+        let mut tracker = Self {
             loot_table: HashMap::new(),
             loot_entry_tracker: vec![],
             detection_mode: LootDetectionMode::OCRChatLootViaStream,
             state: State::Start,
             mutex: Mutex::new(0),
             loot_history: Arc::new(Mutex::new(Vec::new())),
-            item_fetcher: item_fetcher::Fetcher::Default(item_fetcher::DefaultFetcher::new()),
-        }
+            item_fetcher: Arc::new(default_fetcher(
+                Region::default(),
+                Language::default(),
+                disambiguator.clone(),
+            )),
+            disambiguator,
+            ocr_corrector: default_ocr_corrector(&name_index, &grind_spots),
+            item_db,
+            name_index,
+            chat_parser: ChatParser::default(),
+            chat_clock: ChatClock::default(),
+            locales: default_locales(),
+            language: Language::default(),
+            expense_config: default_expense_config(),
+            expenses: vec![],
+            expense_entry_tracker: vec![],
+            price_resolver: PriceResolverConfig::default(),
+            price_rules: Arc::new(default_price_rules()),
+            grind_spots,
+            grind_spot: None,
+            detect_grind_spot: true,
+            spot_detector: SpotDetector::default(),
+            spot_changes: vec![],
+            rejected: 0,
+            unpriced: vec![],
+            session_started_at: chrono::Local::now().timestamp_millis(),
+        };
+        // a custom pack for the default language replaces the built in parser
+        tracker.set_language(Language::default());
+        tracker
     }
     pub fn set_state(&mut self, state: State) {
         self.state = state
@@ -349,37 +1021,117 @@ impl BlackDesertLootTracker {
     }
     pub async fn reset(&mut self) {
         self.loot_entry_tracker.clear();
-        let mut history = self.loot_history.lock().await;
-        history.clear();
+        self.loot_history.lock().await.clear();
         self.loot_table.clear();
+        self.unpriced.clear();
+        self.spot_detector.reset();
+        self.spot_changes.clear();
+        self.rejected = 0;
+        self.expenses.clear();
+        self.expense_entry_tracker.clear();
+        self.session_started_at = chrono::Local::now().timestamp_millis();
+        self.chat_clock = ChatClock::default();
+        if let Some(name) = self.grind_spot().map(|spot| spot.name.clone()) {
+            self.record_spot(name);
+        }
     }
-    fn parse_loot_drop_logs(data: &String) -> Option<LootData> {
-        // find x from right
-        if data.is_empty() {
-            return None;
+    pub async fn session(&self) -> Session {
+        let mut loot: Vec<LootData> = self.loot_table.values().cloned().collect();
+        loot.sort_by(|a, b| a.name.cmp(&b.name));
+        Session {
+            started_at: self.session_started_at,
+            ended_at: chrono::Local::now().timestamp_millis(),
+            grind_spot: self.spot_label().map(str::to_string),
+            spot_changes: self.spot_changes.clone(),
+            expenses: self.expenses.clone(),
+            hourly_costs: self.expense_config.hourly.clone(),
+            rejected: self.rejected,
+            loot,
+            history: self.loot_history.lock().await.clone(),
         }
-        let mut idx = data.len() - 1;
-        let chars: Vec<char> = data.chars().collect();
-        while idx != 0 {
-            let c: String = chars[idx].to_lowercase().collect();
-            if c.nfd().to_string() == "x" {
-                break;
-            }
-
-            idx -= 1;
+    }
+    pub fn grind_spot(&self) -> Option<&GrindSpot> {
+        self.grind_spot.as_ref().map(|filter| &filter.spot)
+    }
+    // selected spot, otherwise the detected one
+    pub fn spot_label(&self) -> Option<&str> {
+        match self.grind_spot() {
+            Some(spot) => Some(spot.name.as_str()),
+            None => self.spot_detector.current(),
         }
-        if idx == 0 {
-            // println!("{}", data);
-            return None;
+    }
+    pub fn language(&self) -> Language {
+        self.language
+    }
+    // chat messages, numbers and item names are read in the client's language from now on
+    pub fn set_language(&mut self, language: Language) {
+        if language != self.language {
+            self.name_index = default_name_index(&self.item_db, language);
+            self.ocr_corrector = default_ocr_corrector(&self.name_index, &self.grind_spots);
         }
-        //
-
-        let (raw_loot, amount_str) = (
-            chars[..idx].iter().collect::<String>(),
-            chars[idx + 1..].iter().collect::<String>(),
+        let locale = self.locales.get(language);
+        match ChatParser::new(&locale) {
+            Ok(parser) => self.chat_parser = parser,
+            Err(err) => println!("{:#}, chat is still read in {}", err, self.language),
+        }
+        self.ocr_corrector.number_format = locale.number_format;
+        self.language = language;
+    }
+    // the spot's drops are preferred when matching names and other items are dropped as misreads.
+    // selecting a spot stops the detection, see `use_spot_detection`
+    pub fn set_grind_spot(&mut self, spot: Option<GrindSpot>) {
+        let filter = spot.map(|spot| SpotFilter::new(spot, &self.grind_spots.common));
+        self.disambiguator.set_known_drops(
+            filter
+                .as_ref()
+                .map(|filter| filter.known_drop_ids())
+                .unwrap_or_default(),
         );
-        let loot = to_title_case(&normalize_spaces(&raw_loot));
-        let amount = extract_number(&amount_str)?;
+        if let Some(filter) = &filter {
+            self.record_spot(filter.spot.name.clone());
+        }
+        self.grind_spot = filter;
+        self.detect_grind_spot = false;
+    }
+    // forget the selected spot and guess it from the loot again
+    pub fn use_spot_detection(&mut self) {
+        self.set_grind_spot(None);
+        self.spot_detector.reset();
+        self.detect_grind_spot = true;
+    }
+    fn detect_spot(&mut self, loot: &[LootData]) {
+        if !self.detect_grind_spot || self.grind_spot.is_some() {
+            return;
+        }
+        let names = loot.iter().map(|v| v.name.as_str());
+        let Some(spot) = self.spot_detector.observe(&self.grind_spots, names) else {
+            return;
+        };
+        let name = spot.name.clone();
+        self.disambiguator
+            .set_known_drops(spot.drops().filter_map(|drop| drop.id));
+        self.record_spot(name);
+    }
+    fn record_spot(&mut self, name: String) {
+        if self
+            .spot_changes
+            .last()
+            .is_some_and(|c| c.grind_spot == name)
+        {
+            return;
+        }
+        self.spot_changes.push(SpotChange {
+            at: chrono::Local::now().timestamp_millis(),
+            grind_spot: name,
+        });
+    }
+    fn parse_loot_drop_logs(corrector: &OcrCorrector, data: &String) -> Option<LootData> {
+        // the last x followed by an amount
+        let (raw_loot, amount) = corrector.split_drop_log(data)?;
+        let loot = to_title_case(&corrector.name(raw_loot));
+        if loot.is_empty() {
+            return None;
+        }
         if amount == 0 {
             // println!("{}", data);
             return None;
@@ -393,100 +1145,56 @@ impl BlackDesertLootTracker {
 
     // why not borrow? i need to modify data
     // why no error? i lazy to bring up error since the data will be empty or not because if it have wrong text pattern will assume it as other log
-    pub fn parse_loot(detection_mode: LootDetectionMode, data: &String) -> Option<LootData> {
+    pub fn parse_loot(
+        detection_mode: LootDetectionMode,
+        corrector: &OcrCorrector,
+        chat: &ChatParser,
+        clock: &mut ChatClock,
+        data: &String,
+    ) -> Option<LootData> {
         match detection_mode {
             LootDetectionMode::OCRDropLogViaStream => {
-                return Self::parse_loot_drop_logs(data);
+                return Self::parse_loot_drop_logs(corrector, data);
             }
             _ => {}
         }
-        let data = normalize_spaces(data);
-        // add matching with error distance
-        if !data.starts_with("You have obtained") {
-            return None;
-        }
-        // manual because im stupid at regex
-        let mut itemname = String::new();
-        let mut idx = 0;
-        // finding name
-        let mut found_name = false;
-        let data_chars: Vec<char> = data.chars().collect();
-        while idx < data.len() {
-            let c = data_chars[idx];
-            idx += 1;
-            if found_name {
-                if c == ']' {
-                    break;
-                }
-                itemname.push(c);
-                continue;
-            }
-            if c == '[' {
-                found_name = true;
-            }
-        }
-        if itemname.is_empty() {
-            return None;
-        }
-        itemname = to_title_case(&itemname);
-        // finding amount
-        let mut amount: u64 = 0;
-        while idx < data.len() {
-            let c = data_chars[idx];
-            if c == '(' {
-                // fallback
-                idx -= 1;
-                break;
-            }
-            idx += 1;
-            let nc = c.to_digit(10);
-            if nc.is_none() {
-                continue;
-            }
-            amount = (amount * 10) + nc.unwrap() as u64;
-        }
-        // default amount is 1.
-        if amount == 0 {
-            amount = 1
-        }
-        let mut hour = 0;
-        let mut minute = 0;
-        // finding hour minute
-        let mut find_mode = 0;
-        while idx < data.len() {
-            let c = data_chars[idx];
-            idx += 1;
-            if c == ':' {
-                find_mode += 1;
-                continue;
-            }
-            let nc = c.to_digit(10);
-            if nc.is_none() {
-                continue;
-            }
-            let nc = nc.unwrap();
-            match find_mode {
-                0 => hour = (hour * 10) + nc,
-                1 => minute = (minute * 10) + nc,
-                _ => {}
-            }
-        }
-        return Some(LootData {
+        let message = chat.parse(corrector, data)?;
+        Self::chat_loot(message, clock)
+    }
+    // only loot counts here, the other events are not in the loot table
+    fn chat_loot(message: ChatMessage, clock: &mut ChatClock) -> Option<LootData> {
+        let loot = match message.event {
+            ChatEvent::Obtained(loot) | ChatEvent::FamilyInventory(loot) => loot,
+            _ => return None,
+        };
+        let timestamp = message.time.and_then(|time| {
+            let at = clock.resolve(time);
+            Local
+                .from_local_datetime(&at)
+                .earliest()
+                .map(|at| at.timestamp_millis())
+        });
+        let time = message.time.unwrap_or_default();
+        Some(LootData {
             id: 0,
-            name: itemname,
-            amount: amount,
-            hour: hour as u8,
-            minute: minute as u8,
+            name: loot.name,
+            amount: loot.amount,
+            hour: time.hour() as u8,
+            minute: time.minute() as u8,
+            timestamp,
             ..Default::default()
-        });
+        })
     }
     pub fn multiple_parse_loot(
         detection_mode: LootDetectionMode,
+        corrector: &OcrCorrector,
+        chat: &ChatParser,
+        clock: &mut ChatClock,
         data: &Vec<String>,
     ) -> Vec<LootData> {
         let mut loot_datas: Vec<LootData> = Vec::new();
         for (_, v) in data.iter().enumerate() {
-            if let Some(loot_data) = Self::parse_loot(detection_mode, v) {
+            if let Some(loot_data) = Self::parse_loot(detection_mode, corrector, chat, clock, v) {
                 // println!("{:?}", loot_data);
                 loot_datas.push(loot_data);
             }
@@ -494,10 +1202,31 @@ impl BlackDesertLootTracker {
         loot_datas
     }
 
-    pub async fn insert(&mut self, new_entry: &Vec<String>) -> u16 {
+    // `captured_at` is the unix millis of the frame the lines were read from
+    pub async fn insert(&mut self, new_entry: &Vec<String>, captured_at: i64) -> u16 {
         // println!("inserting loot data??");
         // let _guard = self.mutex.lock();
-        let mut new_loot_data_entry = Self::multiple_parse_loot(self.detection_mode, new_entry);
+        let mut new_loot_data_entry = match self.detection_mode {
+            // every chat line is parsed once, for the expenses and the loot
+            LootDetectionMode::OCRChatLootViaStream => {
+                let messages: Vec<ChatMessage> = new_entry
+                    .iter()
+                    .filter_map(|line| self.chat_parser.parse(&self.ocr_corrector, line))
+                    .collect();
+                self.insert_expenses(&messages);
+                messages
+                    .into_iter()
+                    .filter_map(|message| Self::chat_loot(message, &mut self.chat_clock))
+                    .collect()
+            }
+            LootDetectionMode::OCRDropLogViaStream => Self::multiple_parse_loot(
+                self.detection_mode,
+                &self.ocr_corrector,
+                &self.chat_parser,
+                &mut self.chat_clock,
+                new_entry,
+            ),
+        };
         if new_loot_data_entry.is_empty() {
             match self.detection_mode {
                 LootDetectionMode::OCRDropLogViaStream => {
@@ -508,55 +1237,9 @@ impl BlackDesertLootTracker {
             return 0;
         }
 
-        if !self.loot_table.is_empty() {
-            for v in new_loot_data_entry.iter_mut() {
-                let mut rate = 0.0;
-                let clean_lootname: String = v
-                    .name
-                    .clone()
-                    .chars()
-                    .filter(|c| c.is_alphanumeric())
-                    .collect();
-                for key in self.loot_table.keys() {
-                    let cleaned_key: String = key
-                        .clone()
-                        .chars()
-                        .filter(|c| c.is_alphanumeric())
-                        .collect();
-                    if cleaned_key.starts_with(&clean_lootname)
-                        || cleaned_key.ends_with(&clean_lootname)
-                    {
-                        v.name = key.clone();
-                        break;
-                    }
-                    let result = strsim::normalized_damerau_levenshtein(
-                        &cleaned_key.to_lowercase(),
-                        &clean_lootname.to_lowercase(),
-                    );
-                    if result > 0.6 && result > rate {
-                        v.name = key.clone();
-                        rate = result;
-                    }
-                }
-            }
-        }
-
-        if true {
-            let mut file = OpenOptions::new()
-                .append(true)
-                .open("data.txt")
-                .await
-                .unwrap();
-
-            for v in new_loot_data_entry.iter() {
-                _ = file
-                    .write_all(format!("{}: {}\n", v.name, v.amount).as_bytes())
-                    .await;
-            }
-            file.write_all(b"=======================\n").await;
+        // rejected loot stays in the diff, the line is still on screen next frame
+        self.normalize_loot(&mut new_loot_data_entry);
 
-            _ = file.flush().await;
-        }
         let diff_loot_data: Vec<LootData>;
         match self.detection_mode {
             LootDetectionMode::OCRChatLootViaStream => {
@@ -581,55 +1264,209 @@ impl BlackDesertLootTracker {
             }
         }
 
+        let drops = self.reject_loot(diff_loot_data);
+        if drops.is_empty() {
+            return 0;
+        }
+        self.record_drops(drops, captured_at).await
+    }
+    // new drops into the history and the loot table, stamped after the diff since the frame
+    // time differs every frame while the drops don't
+    async fn record_drops(&mut self, mut drops: Vec<LootData>, captured_at: i64) -> u16 {
+        for v in drops.iter_mut() {
+            v.timestamp.get_or_insert(captured_at);
+        }
+
+        self.detect_spot(&drops);
         // let mut loot_history = self.loot_history;
         // let mut history: Vec<LootData> = Vec::new();
         let mut history = self.loot_history.as_ref().lock().await;
-        for v in diff_loot_data.iter() {
+        for v in drops.iter() {
             history.push(v.clone());
             let loot_table = self.loot_table.get_mut(&v.name);
             if let Some(entry) = loot_table {
-                entry.amount += v.amount;
-                continue;
-            }
-            let loot_metadata = self.find_loot_metadata(&v.name).await;
-            if loot_metadata.is_none() {
+                entry.amount = entry.amount.saturating_add(v.amount);
                 continue;
             }
-            let metadata = loot_metadata.unwrap();
+            // price is looked up outside, see `take_unpriced` and `resolve_price`
             let new_loot_data = LootData {
-                id: metadata.id,
-                price: metadata.price,
+                id: v.id,
+                price: Silver(0),
                 amount: v.amount,
-                name: metadata.name,
+                name: v.name.clone(),
                 hour: v.hour,
                 minute: v.minute,
+                timestamp: v.timestamp,
+                price_status: PriceStatus::Pending,
+                ..Default::default()
             };
-            // history.push(new_loot_data.clone());
+            self.unpriced.push(v.name.clone());
             self.loot_table
                 .insert(new_loot_data.name.clone(), new_loot_data);
         }
-        return diff_loot_data.len() as u16;
-        // let file = File::create(format!(
-        //     "dump/{}_raw.txt",
-        //     chrono::Local::now().timestamp_millis()
-        // ))
-        // .unwrap();
-        // let mut writer = BufWriter::new(file);
-        // for v in new_entry {
-        //     _ = writeln!(writer, "{}", v);
-        // }
-        // let file = File::create(format!(
-        //     "dump/{}_history.txt",
-        //     chrono::Local::now().timestamp_millis()
-        // ))
-        // .unwrap();
-        // let mut writer = BufWriter::new(file);
-        // for v in history.to_vec() {
-        //     _ = writeln!(writer, "{}: {}", v.name, v.amount);
-        // }
+        drops.len() as u16
+    }
+    pub fn expenses(&self) -> &[Expense] {
+        &self.expenses
+    }
+    // the chat box shows the same lines for a while, only lines below the last seen ones are new
+    fn insert_expenses(&mut self, messages: &[ChatMessage]) {
+        let expenses: Vec<Expense> = messages
+            .iter()
+            .filter_map(|message| self.expense_config.expense(message))
+            .collect();
+        if expenses.is_empty() {
+            return;
+        }
+        let entries: Vec<LootData> = expenses
+            .iter()
+            .map(|e| LootData {
+                name: format!("{:?} {}", e.kind, e.name),
+                amount: e.silver,
+                hour: e.hour,
+                minute: e.minute,
+                ..Default::default()
+            })
+            .collect();
+        let new_count = LootDatas::diff(&self.expense_entry_tracker, &entries).len();
+        self.expenses
+            .extend_from_slice(&expenses[expenses.len() - new_count..]);
+        self.expense_entry_tracker = entries;
     }
-    async fn find_loot_metadata(&self, s: &String) -> Option<Item> {
-        let result = self.item_fetcher.get_data_by_name(&s).await;
+    // canonical names for the loot read from the screen
+    fn normalize_loot(&self, entries: &mut [LootData]) {
+        for v in entries.iter_mut() {
+            if let Some(name) = self.canonical_name(&v.name) {
+                v.name = name;
+            }
+            if v.id == 0 {
+                v.id = self.catalog_id(&v.name).unwrap_or_default();
+            }
+        }
+    }
+    // new drops the selected spot can't drop, or not this many at once, are counted and removed
+    fn reject_loot(&mut self, mut drops: Vec<LootData>) -> Vec<LootData> {
+        let Some(filter) = &self.grind_spot else {
+            return drops;
+        };
+        let before = drops.len();
+        drops.retain(|v| filter.allows(&v.name) && filter.spot.plausible_amount(&v.name, v.amount));
+        self.rejected = self.rejected.saturating_add((before - drops.len()) as u32);
+        drops
+    }
+    // the selected spot first, then the item catalog, then names already in the loot table
+    fn canonical_name(&self, name: &str) -> Option<String> {
+        if let Some(found) = self.grind_spot.as_ref().and_then(|f| f.best_match(name)) {
+            return Some(found.name);
+        }
+        if let Some(found) = self.name_index.best_match(name) {
+            return Some(self.english_name(&found.name));
+        }
+        let mut rate = 0.0;
+        let mut canonical = None;
+        let clean_lootname: String = name.chars().filter(|c| c.is_alphanumeric()).collect();
+        for key in self.loot_table.keys() {
+            let cleaned_key: String = key.chars().filter(|c| c.is_alphanumeric()).collect();
+            if cleaned_key.starts_with(&clean_lootname) || cleaned_key.ends_with(&clean_lootname) {
+                return Some(key.clone());
+            }
+            let result = strsim::normalized_damerau_levenshtein(
+                &cleaned_key.to_lowercase(),
+                &clean_lootname.to_lowercase(),
+            );
+            if result > 0.6 && result > rate {
+                canonical = Some(key.clone());
+                rate = result;
+            }
+        }
+        canonical
+    }
+    // price rules, grind spots and the loot table all use english names, whatever the client reads
+    fn english_name(&self, name: &str) -> String {
+        self.item_db
+            .find_by_name(name)
+            .first()
+            .and_then(|item| item.name("en"))
+            .unwrap_or(name)
+            .to_string()
+    }
+    // only when the name means a single item, otherwise the price lookup picks one
+    fn catalog_id(&self, name: &str) -> Option<u64> {
+        match self.item_db.find_by_name(name).as_slice() {
+            [item] => Some(item.id),
+            _ => None,
+        }
+    }
+    // names inserted since the last call that still need a price
+    pub fn take_unpriced(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.unpriced)
+    }
+    // queue every loot with unknown price again, e.g. after the price source is back
+    pub fn take_unknown_prices(&mut self) -> Vec<String> {
+        let mut names = vec![];
+        for (name, entry) in self.loot_table.iter_mut() {
+            if entry.price_status == PriceStatus::Unknown {
+                entry.price_status = PriceStatus::Pending;
+                names.push(name.clone());
+            }
+        }
+        names
+    }
+    // backfill the price of a pending loot, the entry is renamed to the canonical item name.
+    // None means every lookup failed, the loot is kept with an unknown price
+    pub fn resolve_price(&mut self, name: &str, item: Option<Item>) {
+        let Some(mut entry) = self.loot_table.remove(name) else {
+            return;
+        };
+        let Some(item) = item else {
+            entry.price_status = PriceStatus::Unknown;
+            self.loot_table.insert(name.to_string(), entry);
+            return;
+        };
+        // misreads of this item match it from now on
+        self.name_index.insert(&item.name);
+        self.ocr_corrector.insert_words(&item.name);
+        entry.id = item.id;
+        entry.price = item.price;
+        entry.market_price = item.market_price;
+        entry.vendor_price = item.vendor_price;
+        entry.price_status = PriceStatus::Resolved;
+        // the fetcher may answer in the client's language
+        let name = self.english_name(&item.name);
+        if let Some(existing) = self.loot_table.get_mut(&name) {
+            existing.amount = existing.amount.saturating_add(entry.amount);
+            return;
+        }
+        entry.name = name;
+        self.loot_table.insert(entry.name.clone(), entry);
+    }
+    // the first matching price rule decides what is looked up and which price is used
+    pub async fn find_loot_metadata(
+        fetcher: &item_fetcher::Fetcher,
+        rules: &PriceRules,
+        s: &str,
+    ) -> Option<Item> {
+        let action = rules.find(s).map(|rule| &rule.price);
+        let lookup = match action {
+            Some(PriceAction::Fixed(price)) => {
+                return Some(Item {
+                    id: 0,
+                    name: s.to_string(),
+                    price: Silver(*price),
+                    market_price: Silver(0),
+                    vendor_price: Silver(*price),
+                });
+            }
+            Some(PriceAction::Zero) => {
+                return Some(Item {
+                    name: s.to_string(),
+                    ..Default::default()
+                });
+            }
+            Some(PriceAction::SameAs { item, .. }) => item.as_str(),
+            _ => s,
+        };
+        let result = fetcher.get_data_by_name(lookup).await;
         if let Err(err) = result {
             println!("{}: {}", s, err);
             return None;
@@ -638,17 +1475,39 @@ impl BlackDesertLootTracker {
         // match self.item_fetcher {
         //     item_fetcher::Fetcher::Default(f) => f.get_data_by_name(item_name),
         // }
-        let price = if result.market_sell_price > 0 {
-            Silver(result.market_sell_price)
-        } else {
-            Silver(result.vendor_sell_price)
+        let (mut market_price, mut vendor_price) =
+            (result.market_sell_price, result.vendor_sell_price);
+        let price = match action {
+            Some(PriceAction::Vendor) => {
+                market_price = 0;
+                vendor_price
+            }
+            // no vendor fallback either, the valuation would use it
+            Some(PriceAction::Market) => {
+                vendor_price = 0;
+                market_price
+            }
+            _ if market_price > 0 => market_price,
+            _ => vendor_price,
         };
 
         // self.item_fetcher.(
+        if let Some(PriceAction::SameAs { amount, .. }) = action {
+            // keep the loot name, only the price comes from the other item
+            return Some(Item {
+                id: 0,
+                name: s.to_string(),
+                price: Silver(price.saturating_mul(*amount)),
+                market_price: Silver(market_price.saturating_mul(*amount)),
+                vendor_price: Silver(vendor_price.saturating_mul(*amount)),
+            });
+        }
         Some(Item {
             id: result.id,
             name: result.name,
-            price: price,
+            price: Silver(price),
+            market_price: Silver(market_price),
+            vendor_price: Silver(vendor_price),
         })
     }
     pub fn analyze(&self, input: Vec<AnalyzeCaptureAreaInput>) -> OCRViaStreamConfig {
@@ -665,7 +1524,13 @@ impl BlackDesertLootTracker {
         let abs_cmp = |x: &u32, y: &u32| x.cmp(&y);
         let mut is_found = false;
         for (_, v) in input.iter().enumerate() {
-            let loot_data: Option<LootData> = Self::parse_loot(self.detection_mode, &v.text);
+            let loot_data: Option<LootData> = Self::parse_loot(
+                self.detection_mode,
+                &self.ocr_corrector,
+                &self.chat_parser,
+                &mut ChatClock::default(),
+                &v.text,
+            );
 
             if let Some(_) = loot_data {
                 // println!("{:?}", loot_data);
@@ -744,8 +1609,13 @@ impl BlackDesertLootTracker {
 
         let abs_cmp = |x: &u32, y: &u32| x.cmp(&y);
         let mut is_found = false;
+        // no item names here, only whether the text looks like loot
+        let corrector = OcrCorrector::default();
+        let chat = ChatParser::default();
+        let mut clock = ChatClock::default();
         for (_, v) in input.iter().enumerate() {
-            let loot_data: Option<LootData> = Self::parse_loot(detection_mode, &v.text);
+            let loot_data: Option<LootData> =
+                Self::parse_loot(detection_mode, &corrector, &chat, &mut clock, &v.text);
 
             if let Some(_) = loot_data {
                 // println!("{:?}", loot_data);
@@ -775,13 +1645,16 @@ impl BlackDesertLootTracker {
     }
 }
 
-struct Item {
-    id: u64,
-    name: String,
-    price: Silver,
+#[derive(Default, Debug, Clone)]
+pub struct Item {
+    pub id: u64,
+    pub name: String,
+    pub price: Silver,
+    pub market_price: Silver,
+    pub vendor_price: Silver,
 }
 
-fn normalize_spaces(input: &str) -> String {
+pub(crate) fn normalize_spaces(input: &str) -> String {
     let mut output = String::new();
     let mut was_whitespace = false;
 
@@ -925,7 +1798,7 @@ fn no_spaces(input: &str) -> String {
 //         assert_eq!(history.len(), 6);
 //     }
 // }
-fn to_title_case(s: &str) -> String {
+pub(crate) fn to_title_case(s: &str) -> String {
     s.trim()
         .split_whitespace()
         .map(|word| {
@@ -965,20 +1838,3 @@ fn to_title_case(s: &str) -> String {
 fn is_ocr_misread(expected: &str, actual: &str) -> bool {
     actual.len() < expected.len() && expected.starts_with(actual)
 }
-
-fn extract_number(s: &str) -> Option<u64> {
-    let bytes = s.as_bytes();
-    let mut num = 0;
-    let mut found = false;
-
-    for &b in bytes {
-        if b.is_ascii_digit() {
-            found = true;
-            num = num * 10 + (b - b'0') as u64;
-        } else if found {
-            break; // Stop after the first numeric sequence
-        }
-    }
-
-    found.then_some(num)
-}
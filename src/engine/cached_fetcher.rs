@@ -0,0 +1,447 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+
+use crate::engine::{ItemData, ItemFetcher};
+
+pub const DEFAULT_CACHE_PATH: &str = "cache/item_cache.json";
+
+#[derive(Clone, Copy, Debug)]
+pub struct CacheConfig {
+    // vendor prices almost never change
+    pub vendor_ttl: Duration,
+    pub market_ttl: Duration,
+    // how long after the ttl the old data is still returned while it's refreshed in background
+    pub stale_ttl: Duration,
+    // new entries are written to disk together this long after the first one
+    pub save_delay: Duration,
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self {
+            vendor_ttl: Duration::from_secs(7 * 24 * 60 * 60),
+            market_ttl: Duration::from_secs(60 * 60),
+            stale_ttl: Duration::from_secs(24 * 60 * 60),
+            save_delay: Duration::from_secs(5),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CacheEntry {
+    data: ItemData,
+    // unix seconds
+    fetched_at: i64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Freshness {
+    Fresh,
+    // usable, refresh in background
+    Stale,
+    Expired,
+}
+
+impl CacheEntry {
+    fn freshness(&self, now: i64, config: &CacheConfig) -> Freshness {
+        // items without market price only depend on the vendor price
+        let ttl = if self.data.market_buy_price > 0 || self.data.market_sell_price > 0 {
+            config.market_ttl
+        } else {
+            config.vendor_ttl
+        };
+        let age = now.saturating_sub(self.fetched_at).max(0) as u64;
+        if age < ttl.as_secs() {
+            Freshness::Fresh
+        } else if age < (ttl + config.stale_ttl).as_secs() {
+            Freshness::Stale
+        } else {
+            Freshness::Expired
+        }
+    }
+}
+
+#[derive(Default, Serialize, Deserialize)]
+struct CacheFile {
+    // "<region>:<lowercase name>"
+    entries: HashMap<String, CacheEntry>,
+}
+
+/// Caches the result of another fetcher on disk, keyed by region and item name.
+pub struct CachedFetcher<F: ItemFetcher> {
+    inner: Arc<F>,
+    region: String,
+    path: PathBuf,
+    pub config: CacheConfig,
+    entries: Arc<Mutex<HashMap<String, CacheEntry>>>,
+    // names being refreshed in background
+    revalidating: Arc<Mutex<HashSet<String>>>,
+    save_lock: Arc<Mutex<()>>,
+    save_scheduled: Arc<AtomicBool>,
+    // why the last background save failed, None after a successful one
+    save_error: Arc<Mutex<Option<String>>>,
+}
+
+impl<F: ItemFetcher> fmt::Debug for CachedFetcher<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CachedFetcher")
+            .field("inner", &self.inner)
+            .field("region", &self.region)
+            .field("path", &self.path)
+            .finish()
+    }
+}
+
+impl<F: ItemFetcher + 'static> CachedFetcher<F> {
+    // a missing or broken cache file starts an empty cache
+    pub fn new(inner: F, region: &str, path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+        let entries = std::fs::read(&path)
+            .ok()
+            .and_then(|data| serde_json::from_slice::<CacheFile>(&data).ok())
+            .map(|file| file.entries)
+            .unwrap_or_default();
+        Self {
+            inner: Arc::new(inner),
+            region: region.to_string(),
+            path,
+            config: CacheConfig::default(),
+            entries: Arc::new(Mutex::new(entries)),
+            revalidating: Arc::new(Mutex::new(HashSet::new())),
+            save_lock: Arc::new(Mutex::new(())),
+            save_scheduled: Arc::new(AtomicBool::new(false)),
+            save_error: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    pub fn inner(&self) -> &F {
+        &self.inner
+    }
+
+    fn key(&self, item_name: &str) -> String {
+        format!("{}:{}", self.region, item_name.trim().to_lowercase())
+    }
+
+    pub async fn get_cached_by_id(&self, id: u64) -> Option<ItemData> {
+        let prefix = format!("{}:", self.region);
+        let entries = self.entries.lock().await;
+        entries
+            .iter()
+            .find(|(key, entry)| key.starts_with(&prefix) && entry.data.id == id)
+            .map(|(_, entry)| entry.data.clone())
+    }
+
+    pub async fn clear(&self) -> Result<()> {
+        self.entries.lock().await.clear();
+        self.save().await
+    }
+
+    // write the entries not saved yet now instead of after `save_delay`
+    pub async fn flush(&self) -> Result<()> {
+        self.save().await
+    }
+
+    pub async fn save_error(&self) -> Option<String> {
+        self.save_error.lock().await.clone()
+    }
+
+    async fn store(&self, key: String, data: ItemData) {
+        self.entries.lock().await.insert(
+            key,
+            CacheEntry {
+                data,
+                fetched_at: chrono::Utc::now().timestamp(),
+            },
+        );
+        self.schedule_save();
+    }
+
+    // one write for every entry stored within `save_delay`
+    fn schedule_save(&self) {
+        if self.save_scheduled.swap(true, Ordering::SeqCst) {
+            return;
+        }
+        let cache = self.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(cache.config.save_delay).await;
+            cache.save_scheduled.store(false, Ordering::SeqCst);
+            let result = cache.save().await;
+            *cache.save_error.lock().await = result.err().map(|err| format!("{:#}", err));
+        });
+    }
+
+    async fn save(&self) -> Result<()> {
+        let _guard = self.save_lock.lock().await;
+        let data = {
+            let entries = self.entries.lock().await;
+            serde_json::to_vec(&CacheFile {
+                entries: entries.clone(),
+            })?
+        };
+        if let Some(dir) = self.path.parent() {
+            tokio::fs::create_dir_all(dir).await?;
+        }
+        tokio::fs::write(&self.path, data).await?;
+        Ok(())
+    }
+
+    fn revalidate(&self, key: String, item_name: String) {
+        let cache = self.clone();
+        tokio::spawn(async move {
+            if !cache.revalidating.lock().await.insert(key.clone()) {
+                return;
+            }
+            if let Ok(data) = cache.inner.get_data_by_name(&item_name).await {
+                cache.store(key.clone(), data).await;
+            }
+            cache.revalidating.lock().await.remove(&key);
+        });
+    }
+}
+
+impl<F: ItemFetcher> Clone for CachedFetcher<F> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            region: self.region.clone(),
+            path: self.path.clone(),
+            config: self.config,
+            entries: self.entries.clone(),
+            revalidating: self.revalidating.clone(),
+            save_lock: self.save_lock.clone(),
+            save_scheduled: self.save_scheduled.clone(),
+            save_error: self.save_error.clone(),
+        }
+    }
+}
+
+#[async_trait]
+impl<F: ItemFetcher + 'static> ItemFetcher for CachedFetcher<F> {
+    async fn get_data_by_name(&self, item_name: &str) -> Result<ItemData> {
+        let key = self.key(item_name);
+        let cached = self.entries.lock().await.get(&key).cloned();
+        let now = chrono::Utc::now().timestamp();
+        if let Some(entry) = &cached {
+            match entry.freshness(now, &self.config) {
+                Freshness::Fresh => return Ok(entry.data.clone()),
+                Freshness::Stale => {
+                    self.revalidate(key, item_name.to_string());
+                    return Ok(entry.data.clone());
+                }
+                Freshness::Expired => {}
+            }
+        }
+        match self.inner.get_data_by_name(item_name).await {
+            Ok(data) => {
+                self.store(key, data.clone()).await;
+                Ok(data)
+            }
+            // old price is better than no price
+            Err(err) => cached.map(|entry| entry.data).ok_or(err),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_cached_fetcher {
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    use anyhow::{Result, anyhow};
+    use async_trait::async_trait;
+
+    use crate::engine::cached_fetcher::{CacheConfig, CacheEntry, CachedFetcher, Freshness};
+    use crate::engine::{ItemData, ItemFetcher};
+
+    #[derive(Debug, Default)]
+    struct CountingFetcher {
+        calls: Arc<AtomicUsize>,
+        fail: bool,
+    }
+
+    #[async_trait]
+    impl ItemFetcher for CountingFetcher {
+        async fn get_data_by_name(&self, item_name: &str) -> Result<ItemData> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            if self.fail {
+                return Err(anyhow!("offline"));
+            }
+            Ok(item(item_name, 100))
+        }
+    }
+
+    fn item(name: &str, market_price: u64) -> ItemData {
+        ItemData {
+            id: 16001,
+            name: name.to_string(),
+            vendor_buy_price: 0,
+            vendor_sell_price: 10,
+            market_buy_price: market_price,
+            market_sell_price: market_price,
+        }
+    }
+
+    fn cache_path(name: &str) -> std::path::PathBuf {
+        let path = std::env::temp_dir().join(format!(
+            "fan_bd_{}_{}.json",
+            name,
+            chrono::Utc::now().timestamp_nanos_opt().unwrap()
+        ));
+        _ = std::fs::remove_file(&path);
+        path
+    }
+
+    #[test]
+    fn freshness_by_price_kind() {
+        let config = CacheConfig {
+            vendor_ttl: Duration::from_secs(100),
+            market_ttl: Duration::from_secs(10),
+            stale_ttl: Duration::from_secs(5),
+            ..Default::default()
+        };
+        let market = CacheEntry {
+            data: item("Black Stone", 100),
+            fetched_at: 0,
+        };
+        assert_eq!(market.freshness(9, &config), Freshness::Fresh);
+        assert_eq!(market.freshness(12, &config), Freshness::Stale);
+        assert_eq!(market.freshness(15, &config), Freshness::Expired);
+        let vendor = CacheEntry {
+            data: item("Swamp Leaves", 0),
+            fetched_at: 0,
+        };
+        assert_eq!(vendor.freshness(50, &config), Freshness::Fresh);
+    }
+
+    #[tokio::test]
+    async fn cache_hit_and_persist() {
+        let path = cache_path("hit");
+        let calls = Arc::new(AtomicUsize::new(0));
+        let fetcher = CachedFetcher::new(
+            CountingFetcher {
+                calls: calls.clone(),
+                fail: false,
+            },
+            "SEA",
+            &path,
+        );
+        fetcher.get_data_by_name("Black Stone").await.unwrap();
+        fetcher.get_data_by_name("black stone ").await.unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        assert!(fetcher.get_cached_by_id(16001).await.is_some());
+        fetcher.flush().await.unwrap();
+
+        // another session, other region is a different key
+        let reopened = CachedFetcher::new(
+            CountingFetcher {
+                calls: calls.clone(),
+                fail: true,
+            },
+            "SEA",
+            &path,
+        );
+        assert!(reopened.get_data_by_name("Black Stone").await.is_ok());
+        let other_region = CachedFetcher::new(
+            CountingFetcher {
+                calls: calls.clone(),
+                fail: true,
+            },
+            "NA",
+            &path,
+        );
+        assert!(other_region.get_data_by_name("Black Stone").await.is_err());
+        _ = std::fs::remove_file(&path);
+    }
+
+    #[tokio::test]
+    async fn stale_while_revalidate() {
+        let path = cache_path("stale");
+        let calls = Arc::new(AtomicUsize::new(0));
+        let mut fetcher = CachedFetcher::new(
+            CountingFetcher {
+                calls: calls.clone(),
+                fail: false,
+            },
+            "SEA",
+            &path,
+        );
+        fetcher.config.market_ttl = Duration::ZERO;
+        fetcher.get_data_by_name("Black Stone").await.unwrap();
+        // stale, served from cache and refreshed in background
+        fetcher.get_data_by_name("Black Stone").await.unwrap();
+        for _ in 0..100 {
+            if calls.load(Ordering::SeqCst) == 2 && fetcher.revalidating.lock().await.is_empty() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+        fetcher.flush().await.unwrap();
+
+        // expired and the source is down, the old data is still returned
+        let mut offline = CachedFetcher::new(
+            CountingFetcher {
+                calls: calls.clone(),
+                fail: true,
+            },
+            "SEA",
+            &path,
+        );
+        offline.config.market_ttl = Duration::ZERO;
+        offline.config.stale_ttl = Duration::ZERO;
+        assert!(offline.get_data_by_name("Black Stone").await.is_ok());
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+        _ = std::fs::remove_file(&path);
+    }
+
+    #[tokio::test]
+    async fn saves_are_batched() {
+        let path = cache_path("batched");
+        let mut fetcher = CachedFetcher::new(CountingFetcher::default(), "SEA", &path);
+        fetcher.config.save_delay = Duration::from_millis(50);
+        fetcher.get_data_by_name("Black Stone").await.unwrap();
+        fetcher.get_data_by_name("Swamp Leaves").await.unwrap();
+        assert!(!path.exists());
+        for _ in 0..100 {
+            if path.exists() && !fetcher.save_scheduled.load(Ordering::SeqCst) {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        let reopened = CachedFetcher::new(CountingFetcher::default(), "SEA", &path);
+        assert_eq!(reopened.entries.lock().await.len(), 2);
+        _ = std::fs::remove_file(&path);
+    }
+
+    #[tokio::test]
+    async fn failed_save_is_kept() {
+        // a file where the cache directory should be
+        let blocker = cache_path("blocker");
+        std::fs::write(&blocker, "").unwrap();
+        let mut fetcher = CachedFetcher::new(
+            CountingFetcher::default(),
+            "SEA",
+            blocker.join("cache.json"),
+        );
+        fetcher.config.save_delay = Duration::ZERO;
+        fetcher.get_data_by_name("Black Stone").await.unwrap();
+        for _ in 0..100 {
+            if fetcher.save_error().await.is_some() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert!(fetcher.save_error().await.is_some());
+        _ = std::fs::remove_file(&blocker);
+    }
+}
@@ -0,0 +1,381 @@
+use anyhow::{Context, Result};
+use chrono::{NaiveDateTime, NaiveTime};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::engine::blackdesert::{normalize_spaces, to_title_case};
+use crate::engine::{LocalePack, OcrCorrector};
+
+// how close the text before the item has to be to the expected phrase
+const PREFIX_MIN_SCORE: f64 = 0.8;
+
+// `[Name]` followed by the amount, the amount is everything up to the next keyword
+const ITEM: &str = r"\[(?P<name>[^\[\]]+)\](?P<amount>[^\[\]()]*?)";
+// the amount in silver, has to start with a digit
+const SILVER: &str = r"(?P<silver>[0-9][^()]*?)";
+// optional `(hh:mm)` or `(h:mm PM)` at the end, the closing bracket is often cut off
+const TIME: &str = r"\s*(?:\((?P<time>[^()]*)\)?)?\s*$";
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ChatLoot {
+    pub name: String,
+    pub amount: u64,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ChatEvent {
+    // looted into the inventory
+    Obtained(ChatLoot),
+    // looted straight into the family inventory
+    FamilyInventory(ChatLoot),
+    // destroyed, consumed or dropped
+    Lost(ChatLoot),
+    // silver moved into storage, not loot
+    Deposited { silver: u64 },
+    SoldToNpc { loot: ChatLoot, silver: u64 },
+    // bought from an npc
+    Purchased { loot: ChatLoot, silver: u64 },
+    Repaired { silver: u64 },
+}
+
+// between hour and minute, ocr reads the colon as any of these
+const TIME_SEPARATORS: [char; 3] = [':', ';', '.'];
+const AM: [&str; 5] = ["am", "a.m", "오전", "午前", "上午"];
+const PM: [&str; 5] = ["pm", "p.m", "오후", "午後", "下午"];
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ChatMessage {
+    pub event: ChatEvent,
+    // None when the line has no time or it isn't a valid time of day
+    pub time: Option<NaiveTime>,
+}
+
+/// Puts chat times on the calendar, the chat only shows the time of day.
+#[derive(Clone, Debug)]
+pub struct ChatClock {
+    // latest message so far, starts at the session start
+    last: NaiveDateTime,
+}
+
+impl Default for ChatClock {
+    fn default() -> Self {
+        Self::new(chrono::Local::now().naive_local())
+    }
+}
+
+impl ChatClock {
+    pub fn new(start: NaiveDateTime) -> Self {
+        Self { last: start }
+    }
+
+    // the day that puts the time closest to the latest message. times after midnight move
+    // to the next day and lines from before midnight still on screen stay on the day before
+    pub fn resolve(&mut self, time: NaiveTime) -> NaiveDateTime {
+        let date = self.last.date();
+        let at = [date.pred_opt(), Some(date), date.succ_opt()]
+            .into_iter()
+            .flatten()
+            .map(|date| date.and_time(time))
+            .min_by_key(|at| (*at - self.last).num_seconds().abs())
+            .unwrap_or(date.and_time(time));
+        self.last = self.last.max(at);
+        at
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChatEventKind {
+    Obtained,
+    FamilyInventory,
+    Lost,
+    Deposited,
+    SoldToNpc,
+    Purchased,
+    Repaired,
+}
+
+/// One message of a locale pack, e.g. `you have obtained` + `{item}`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MessageTemplate {
+    pub kind: ChatEventKind,
+    // lowercase words in front of the body, compared loosely since ocr misreads them
+    #[serde(default)]
+    pub prefix: String,
+    // regex with `{item}` and `{silver}` where the loot and the silver amount go
+    pub body: String,
+}
+
+#[derive(Clone, Debug)]
+struct ChatPattern {
+    kind: ChatEventKind,
+    prefix: String,
+    // groups: prefix, name, amount, silver, time
+    regex: Regex,
+}
+
+/// Turns chat lines into typed events, one regex per message type.
+#[derive(Clone, Debug)]
+pub struct ChatParser {
+    patterns: Vec<ChatPattern>,
+}
+
+impl Default for ChatParser {
+    fn default() -> Self {
+        Self::new(&LocalePack::english()).unwrap()
+    }
+}
+
+impl ChatParser {
+    pub fn new(locale: &LocalePack) -> Result<Self> {
+        let patterns = locale
+            .messages
+            .iter()
+            .map(ChatPattern::new)
+            .collect::<Result<Vec<_>>>()
+            .with_context(|| format!("bad message template for {}", locale.language))?;
+        Ok(Self { patterns })
+    }
+
+    pub fn parse(&self, corrector: &OcrCorrector, line: &str) -> Option<ChatMessage> {
+        let line = normalize_spaces(line);
+        self.patterns
+            .iter()
+            .find_map(|pattern| pattern.parse(corrector, &line))
+    }
+}
+
+impl ChatPattern {
+    fn new(template: &MessageTemplate) -> Result<Self> {
+        // the prefix ends where the body starts, at the `[` of the item or the first digit
+        let prefix = if template.body.starts_with("{silver}") {
+            "[^0-9]"
+        } else {
+            r"[^\[]"
+        };
+        let body = template
+            .body
+            .replace("{item}", ITEM)
+            .replace("{silver}", SILVER);
+        let regex = Regex::new(&format!("(?i)^(?P<prefix>{}*?){}{}", prefix, body, TIME))
+            .with_context(|| format!("invalid {:?} message {:?}", template.kind, template.body))?;
+        Ok(Self {
+            kind: template.kind,
+            prefix: template.prefix.to_lowercase(),
+            regex,
+        })
+    }
+
+    fn parse(&self, corrector: &OcrCorrector, line: &str) -> Option<ChatMessage> {
+        let caps = self.regex.captures(line)?;
+        if !prefix_matches(&self.prefix, caps.name("prefix").map_or("", |m| m.as_str())) {
+            return None;
+        }
+        let loot = || -> Option<ChatLoot> {
+            let name = to_title_case(&corrector.name(caps.name("name")?.as_str()));
+            if name.is_empty() {
+                return None;
+            }
+            let amount = caps.name("amount").map_or("", |m| m.as_str());
+            let amount = amount
+                .trim_start()
+                .trim_start_matches(|c| corrector.matrix.is_separator(c));
+            // no amount means one, an amount that doesn't read (zero, too big) drops the line
+            let amount = if amount.chars().any(char::is_alphanumeric) {
+                corrector.amount(amount).filter(|amount| *amount > 0)?
+            } else {
+                1
+            };
+            Some(ChatLoot { name, amount })
+        };
+        let silver = || corrector.silver(caps.name("silver")?.as_str());
+        let event = match self.kind {
+            ChatEventKind::Obtained => ChatEvent::Obtained(loot()?),
+            ChatEventKind::FamilyInventory => ChatEvent::FamilyInventory(loot()?),
+            ChatEventKind::Lost => ChatEvent::Lost(loot()?),
+            ChatEventKind::Deposited => ChatEvent::Deposited { silver: silver()? },
+            ChatEventKind::SoldToNpc => ChatEvent::SoldToNpc {
+                loot: loot()?,
+                silver: silver()?,
+            },
+            ChatEventKind::Purchased => ChatEvent::Purchased {
+                loot: loot()?,
+                silver: silver()?,
+            },
+            ChatEventKind::Repaired => ChatEvent::Repaired { silver: silver()? },
+        };
+        Some(ChatMessage {
+            event,
+            time: caps
+                .name("time")
+                .and_then(|m| parse_time(corrector, m.as_str())),
+        })
+    }
+}
+
+fn prefix_matches(expected: &str, actual: &str) -> bool {
+    let actual = actual
+        .trim_matches(|c: char| !c.is_alphanumeric())
+        .to_lowercase();
+    if expected.is_empty() {
+        return actual.is_empty();
+    }
+    strsim::normalized_levenshtein(expected, &actual) >= PREFIX_MIN_SCORE
+}
+
+// "16:08", "4:08 PM" or "오후 4:08", misread digits are fixed. None for "77:99" or "13:00 PM"
+fn parse_time(corrector: &OcrCorrector, s: &str) -> Option<NaiveTime> {
+    let lower = s.to_lowercase();
+    let pm = PM.iter().any(|marker| lower.contains(marker));
+    let am = AM.iter().any(|marker| lower.contains(marker));
+    // everything but digits and separators is dropped, the am/pm letters aren't confusions
+    let clean: String = s
+        .chars()
+        .map(|c| corrector.matrix.digits.get(&c).copied().unwrap_or(c))
+        .filter(|c| c.is_ascii_digit() || TIME_SEPARATORS.contains(c))
+        .collect();
+    let (hour, minute) = clean
+        .trim_matches(TIME_SEPARATORS)
+        .split_once(TIME_SEPARATORS)?;
+    let minute = minute.split(TIME_SEPARATORS).next()?;
+    if minute.len() != 2 {
+        return None;
+    }
+    let hour: u32 = hour.parse().ok()?;
+    let minute: u32 = minute.parse().ok()?;
+    let hour = match (am, pm) {
+        (false, false) => hour,
+        (true, true) => return None,
+        // 12 AM is midnight, 12 PM is noon
+        (am, _) if (1..=12).contains(&hour) => hour % 12 + if am { 0 } else { 12 },
+        _ => return None,
+    };
+    NaiveTime::from_hms_opt(hour, minute, 0)
+}
+
+#[cfg(test)]
+mod test_chat {
+    use chrono::{NaiveDate, NaiveTime};
+
+    use crate::engine::{ChatClock, ChatEvent, ChatLoot, ChatParser, OcrCorrector};
+
+    fn loot(name: &str, amount: u64) -> ChatLoot {
+        ChatLoot {
+            name: name.to_string(),
+            amount,
+        }
+    }
+
+    #[test]
+    fn message_types() {
+        let parser = ChatParser::default();
+        let corrector = OcrCorrector::default();
+        let cases = [
+            (
+                "You have obtained [Black Stone]x7. (16:08)",
+                Some(ChatEvent::Obtained(loot("Black Stone", 7))),
+            ),
+            (
+                "You have obtained  [Black Stone]x7.",
+                Some(ChatEvent::Obtained(loot("Black Stone", 7))),
+            ),
+            (
+                "You have obtained [black stone].(16:08)",
+                Some(ChatEvent::Obtained(loot("Black Stone", 1))),
+            ),
+            (
+                "You have obtained [Silver] x 1,234,567 (16:08)",
+                Some(ChatEvent::Obtained(loot("Silver", 1_234_567))),
+            ),
+            // ocr misreads in the phrase
+            (
+                "You have obtalned [Black Stone]x7. (16:08)",
+                Some(ChatEvent::Obtained(loot("Black Stone", 7))),
+            ),
+            (
+                "Youhave obtained [Black Stone]x7.",
+                Some(ChatEvent::Obtained(loot("Black Stone", 7))),
+            ),
+            (
+                "[Swamp Leaves]x2 has been moved to the Family Inventory. (16:09)",
+                Some(ChatEvent::FamilyInventory(loot("Swamp Leaves", 2))),
+            ),
+            (
+                "You have lost [Black Stone]x1. (16:10)",
+                Some(ChatEvent::Lost(loot("Black Stone", 1))),
+            ),
+            (
+                "You have deposited 1,234,567 Silver into the storage. (16:11)",
+                Some(ChatEvent::Deposited { silver: 1_234_567 }),
+            ),
+            (
+                "You have sold [Swamp Leaves]x20 for 24,800 Silver. (16:12)",
+                Some(ChatEvent::SoldToNpc {
+                    loot: loot("Swamp Leaves", 20),
+                    silver: 24_800,
+                }),
+            ),
+            ("You have gained 120 Contribution EXP.", None),
+            ("Your inventory is full.", None),
+            ("You have [Black Stone]x7.", None),
+            ("", None),
+        ];
+        for (line, expected) in cases {
+            let event = parser.parse(&corrector, line).map(|message| message.event);
+            assert_eq!(event, expected, "{:?}", line);
+        }
+    }
+
+    #[test]
+    fn time() {
+        let parser = ChatParser::default();
+        let corrector = OcrCorrector::default();
+        let message = parser
+            .parse(&corrector, "You have obtained [Black Stone]x7. (16:08")
+            .unwrap();
+        assert_eq!(message.time, NaiveTime::from_hms_opt(16, 8, 0));
+        let message = parser
+            .parse(&corrector, "You have obtained [Black Stone]x7.")
+            .unwrap();
+        assert_eq!(message.time, None);
+
+        let cases = [
+            ("(16:08)", Some((16, 8))),
+            ("(00:00)", Some((0, 0))),
+            ("(4:08 PM)", Some((16, 8))),
+            ("(4:08PM)", Some((16, 8))),
+            ("(4:08 p.m.)", Some((16, 8))),
+            ("(12:05 AM)", Some((0, 5))),
+            ("(12:05 PM)", Some((12, 5))),
+            ("(오후 4:08)", Some((16, 8))),
+            ("(l6:O8)", Some((16, 8))),
+            ("(16;08)", Some((16, 8))),
+            ("(77:99)", None),
+            ("(24:00)", None),
+            ("(13:00 PM)", None),
+            ("(0:30 AM)", None),
+            ("(16:8)", None),
+            ("(1608)", None),
+        ];
+        for (time, expected) in cases {
+            let line = format!("You have obtained [Black Stone]x7. {}", time);
+            let message = parser.parse(&corrector, &line).unwrap();
+            let expected = expected.and_then(|(h, m)| NaiveTime::from_hms_opt(h, m, 0));
+            assert_eq!(message.time, expected, "{:?}", time);
+        }
+    }
+
+    #[test]
+    fn midnight_rollover() {
+        let day = NaiveDate::from_ymd_opt(2025, 7, 26).unwrap();
+        let at = |h, m| NaiveTime::from_hms_opt(h, m, 0).unwrap();
+        let mut clock = ChatClock::new(day.and_time(at(23, 50)));
+        assert_eq!(clock.resolve(at(23, 58)), day.and_time(at(23, 58)));
+        let next_day = day.succ_opt().unwrap();
+        assert_eq!(clock.resolve(at(0, 3)), next_day.and_time(at(0, 3)));
+        // the line from before midnight is still on screen
+        assert_eq!(clock.resolve(at(23, 58)), day.and_time(at(23, 58)));
+        assert_eq!(clock.resolve(at(0, 4)), next_day.and_time(at(0, 4)));
+        assert_eq!(clock.resolve(at(9, 0)), next_day.and_time(at(9, 0)));
+    }
+}
@@ -0,0 +1,282 @@
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::sync::{Mutex, RwLock};
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+pub const ITEM_CHOICES_PATH: &str = "config/item_choices.json";
+
+// score difference under which the pick is not obvious and is recorded for the user
+const AMBIGUOUS_MARGIN: i32 = 10;
+
+/// One item sharing the searched name, fields the source doesn't know are None.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Candidate {
+    pub id: u64,
+    pub grade: Option<i64>,
+    pub category: Option<String>,
+    pub has_market_data: Option<bool>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PendingChoice {
+    pub name: String,
+    pub candidates: Vec<u64>,
+    // what was used until the user confirms
+    pub picked: u64,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+struct ItemChoices {
+    // lowercase name to id, set by the user and always used
+    confirmed: HashMap<String, u64>,
+    // lowercase name to the id picked last time, only clear picks are kept
+    resolved: HashMap<String, u64>,
+    pending: Vec<PendingChoice>,
+}
+
+/// Picks the item id when several items share a name, and remembers the choice.
+#[derive(Debug, Default)]
+pub struct Disambiguator {
+    choices: Mutex<ItemChoices>,
+    // ids dropped at the current grind spot
+    known_drops: RwLock<HashSet<u64>>,
+    // None keeps the choices in memory only
+    path: Option<PathBuf>,
+}
+
+impl Disambiguator {
+    // a missing or broken file starts without choices
+    pub fn load(path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+        let choices = std::fs::read(&path)
+            .ok()
+            .and_then(|data| serde_json::from_slice(&data).ok())
+            .unwrap_or_default();
+        Self {
+            choices: Mutex::new(choices),
+            known_drops: RwLock::new(HashSet::new()),
+            path: Some(path),
+        }
+    }
+
+    pub fn set_known_drops(&self, ids: impl IntoIterator<Item = u64>) {
+        *self.known_drops.write().unwrap() = ids.into_iter().collect();
+    }
+
+    // picks were close, waiting for the user to confirm
+    pub fn pending(&self) -> Vec<PendingChoice> {
+        self.choices.lock().unwrap().pending.clone()
+    }
+
+    pub fn confirmed(&self, name: &str) -> Option<u64> {
+        self.choices
+            .lock()
+            .unwrap()
+            .confirmed
+            .get(&key(name))
+            .copied()
+    }
+
+    pub async fn confirm(&self, name: &str, id: u64) -> Result<()> {
+        {
+            let mut choices = self.choices.lock().unwrap();
+            let name = key(name);
+            choices.pending.retain(|p| key(&p.name) != name);
+            choices.resolved.insert(name.clone(), id);
+            choices.confirmed.insert(name, id);
+        }
+        self.save().await
+    }
+
+    // returns the picked id and whether a new ambiguous pick was recorded
+    pub fn pick(&self, name: &str, candidates: &[Candidate]) -> Option<(u64, bool)> {
+        let name_key = key(name);
+        let mut choices = self.choices.lock().unwrap();
+        if let Some(id) = choices.confirmed.get(&name_key) {
+            if candidates.is_empty() || candidates.iter().any(|c| c.id == *id) {
+                return Some((*id, false));
+            }
+        }
+        let mut scored: Vec<(i32, u64)> = {
+            let known_drops = self.known_drops.read().unwrap();
+            let resolved = choices.resolved.get(&name_key).copied();
+            let fallback = fallback_pick(candidates);
+            candidates
+                .iter()
+                .map(|c| (score(c, resolved, fallback, &known_drops), c.id))
+                .collect()
+        };
+        // best first, then higher id
+        scored.sort_by(|a, b| b.cmp(a));
+        let (best_score, best) = *scored.first()?;
+        let close = scored
+            .get(1)
+            .is_some_and(|(second, _)| best_score - second < AMBIGUOUS_MARGIN);
+        // a guess is not remembered, the next pick may know more (e.g. the grind spot)
+        if !close {
+            choices.resolved.insert(name_key.clone(), best);
+        }
+        let recorded = close && !choices.pending.iter().any(|p| key(&p.name) == name_key);
+        if recorded {
+            let mut ids: Vec<u64> = candidates.iter().map(|c| c.id).collect();
+            ids.sort();
+            choices.pending.push(PendingChoice {
+                name: name.trim().to_string(),
+                candidates: ids,
+                picked: best,
+            });
+        }
+        Some((best, recorded))
+    }
+
+    pub async fn save(&self) -> Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let data = serde_json::to_vec_pretty(&*self.choices.lock().unwrap())?;
+        if let Some(dir) = path.parent() {
+            tokio::fs::create_dir_all(dir).await?;
+        }
+        tokio::fs::write(path, data).await?;
+        Ok(())
+    }
+}
+
+fn key(name: &str) -> String {
+    name.trim().to_lowercase()
+}
+
+// the old rule: sorted by id, trash loot always has a higher id than the older item
+// sharing its name, other items keep the lowest id
+fn fallback_pick(candidates: &[Candidate]) -> Option<u64> {
+    let first = candidates.iter().min_by_key(|c| c.id)?;
+    if first.grade == Some(0) {
+        candidates.iter().map(|c| c.id).max()
+    } else {
+        Some(first.id)
+    }
+}
+
+fn score(
+    candidate: &Candidate,
+    resolved: Option<u64>,
+    fallback: Option<u64>,
+    known_drops: &HashSet<u64>,
+) -> i32 {
+    let mut score = 0;
+    // the spot being farmed right now says more than an earlier pick
+    if known_drops.contains(&candidate.id) {
+        score += 150;
+    }
+    if resolved == Some(candidate.id) {
+        score += 100;
+    }
+    if let Some(category) = &candidate.category {
+        if category.to_lowercase().contains("trash") {
+            score += 20;
+        }
+    }
+    // event and quest copies of an item are usually not on the market
+    if candidate.has_market_data == Some(true) {
+        score += 5;
+    }
+    if fallback == Some(candidate.id) {
+        score += 3;
+    }
+    score
+}
+
+#[cfg(test)]
+mod test_disambiguation {
+    use crate::engine::{Candidate, Disambiguator};
+
+    fn candidate(id: u64, grade: i64, category: &str) -> Candidate {
+        Candidate {
+            id,
+            grade: Some(grade),
+            category: Some(category.to_string()),
+            has_market_data: None,
+        }
+    }
+
+    #[test]
+    fn fallback_is_old_heuristic() {
+        let picker = Disambiguator::default();
+        let plain = |id, grade| Candidate {
+            id,
+            grade: Some(grade),
+            ..Default::default()
+        };
+        assert_eq!(
+            picker.pick("Narc Magic Mark", &[plain(44195, 0), plain(59820, 0)]),
+            Some((59820, true))
+        );
+        assert_eq!(
+            picker.pick("Caphras Stone", &[plain(721003, 2), plain(721010, 0)]),
+            Some((721003, true))
+        );
+        assert_eq!(picker.pick("Nothing", &[]), None);
+    }
+
+    #[test]
+    fn scores_category_and_drops() {
+        let picker = Disambiguator::default();
+        let candidates = [
+            candidate(100, 1, "Material"),
+            candidate(200, 0, "Trash Loot"),
+            candidate(300, 0, "Material"),
+        ];
+        assert_eq!(picker.pick("Mark", &candidates), Some((200, false)));
+        assert!(picker.pending().is_empty());
+
+        let picker = Disambiguator::default();
+        picker.set_known_drops([300]);
+        assert_eq!(picker.pick("Mark", &candidates).unwrap().0, 300);
+    }
+
+    #[test]
+    fn guesses_are_not_remembered() {
+        let picker = Disambiguator::default();
+        let candidates = [candidate(1, 1, "Material"), candidate(2, 1, "Material")];
+        assert_eq!(picker.pick("Stone", &candidates), Some((1, true)));
+        // the spot's drop wins over the earlier guess, recorded only once
+        picker.set_known_drops([2]);
+        assert_eq!(picker.pick("stone", &candidates), Some((2, false)));
+        assert_eq!(picker.pending().len(), 1);
+    }
+
+    #[test]
+    fn remembers_clear_picks() {
+        let picker = Disambiguator::default();
+        let candidates = [candidate(1, 1, "Trash Loot"), candidate(2, 1, "Material")];
+        assert_eq!(picker.pick("Stone", &candidates), Some((1, false)));
+        // a drop of the spot still beats the remembered pick
+        picker.set_known_drops([2]);
+        assert_eq!(picker.pick("stone", &candidates), Some((2, false)));
+        // which is remembered in turn since it was a clear pick
+        picker.set_known_drops([]);
+        assert_eq!(picker.pick("stone", &candidates), Some((2, false)));
+    }
+
+    #[tokio::test]
+    async fn confirmed_choice_is_kept() {
+        let path = std::env::temp_dir().join(format!(
+            "fan_bd_choices_{}.json",
+            chrono::Utc::now().timestamp_nanos_opt().unwrap()
+        ));
+        let picker = Disambiguator::load(&path);
+        let candidates = [candidate(1, 1, "Material"), candidate(2, 1, "Material")];
+        picker.pick("Stone", &candidates);
+        assert_eq!(picker.pending()[0].candidates, vec![1, 2]);
+        picker.confirm("Stone", 2).await.unwrap();
+        assert!(picker.pending().is_empty());
+
+        let reopened = Disambiguator::load(&path);
+        assert_eq!(reopened.confirmed("STONE"), Some(2));
+        assert_eq!(reopened.pick("Stone", &candidates), Some((2, false)));
+        _ = std::fs::remove_file(&path);
+    }
+}
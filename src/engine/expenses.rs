@@ -0,0 +1,243 @@
+use std::fmt;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use chrono::Timelike;
+use serde::{Deserialize, Serialize};
+
+use crate::engine::{ChatEvent, ChatMessage, LootValue, Session, Silver, ValuationContext};
+
+pub const EXPENSES_PATH: &str = "config/expenses.json";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExpenseKind {
+    Purchase,
+    // bought from an npc and used up while grinding, e.g. potions
+    Consumable,
+    Repair,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Expense {
+    pub kind: ExpenseKind,
+    // bought item, empty for repairs
+    pub name: String,
+    pub amount: u64,
+    pub silver: u64,
+    pub hour: u8,
+    pub minute: u8,
+}
+
+// buffs the chat doesn't show, entered by the user
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HourlyCost {
+    pub name: String,
+    pub silver_per_hour: u64,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ExpenseConfig {
+    // purchases with a name containing any of these are consumables
+    pub consumables: Vec<String>,
+    pub hourly: Vec<HourlyCost>,
+}
+
+impl Default for ExpenseConfig {
+    fn default() -> Self {
+        Self {
+            consumables: ["potion", "elixir", "draught", "meal", "food", "perfume"]
+                .into_iter()
+                .map(str::to_string)
+                .collect(),
+            hourly: vec![],
+        }
+    }
+}
+
+impl ExpenseConfig {
+    // a missing file gives the default config
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let data =
+            std::fs::read(path).with_context(|| format!("failed to read {}", path.display()))?;
+        serde_json::from_slice(&data).with_context(|| format!("failed to parse {}", path.display()))
+    }
+
+    // None for chat messages that don't cost silver
+    pub fn expense(&self, message: &ChatMessage) -> Option<Expense> {
+        let (kind, name, amount, silver) = match &message.event {
+            ChatEvent::Purchased { loot, silver } => {
+                let lower = loot.name.to_lowercase();
+                let kind = if self
+                    .consumables
+                    .iter()
+                    .any(|c| lower.contains(&c.to_lowercase()))
+                {
+                    ExpenseKind::Consumable
+                } else {
+                    ExpenseKind::Purchase
+                };
+                (kind, loot.name.clone(), loot.amount, *silver)
+            }
+            ChatEvent::Repaired { silver } => (ExpenseKind::Repair, String::new(), 1, *silver),
+            _ => return None,
+        };
+        let time = message.time.unwrap_or_default();
+        Some(Expense {
+            kind,
+            name,
+            amount,
+            silver,
+            hour: time.hour() as u8,
+            minute: time.minute() as u8,
+        })
+    }
+}
+
+fn signed(silver: Silver) -> i64 {
+    i64::try_from(silver.value()).unwrap_or(i64::MAX)
+}
+
+/// Loot value minus what was spent during the session.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Profit {
+    pub gross: LootValue,
+    pub expenses: Silver,
+    // net market value of the loot minus expenses, negative on a bad session
+    pub net: i64,
+    pub hours: f64,
+}
+
+impl Profit {
+    pub fn of(session: &Session, ctx: &ValuationContext) -> Self {
+        let mut gross = LootValue::default();
+        for loot in &session.loot {
+            gross += loot.calculate_with(ctx);
+        }
+        let hours = (session.ended_at - session.started_at).max(0) as f64 / 3_600_000.0;
+        let chat = session
+            .expenses
+            .iter()
+            .fold(0u64, |sum, e| sum.saturating_add(e.silver));
+        let hourly = session
+            .hourly_costs
+            .iter()
+            .map(|c| (c.silver_per_hour as f64 * hours) as u64)
+            .fold(0u64, u64::saturating_add);
+        let expenses = Silver::new(chat.saturating_add(hourly));
+        Self {
+            gross,
+            expenses,
+            net: signed(gross.net_market).saturating_sub(signed(expenses)),
+            hours,
+        }
+    }
+
+    pub fn net_per_hour(&self) -> i64 {
+        if self.hours <= 0.0 {
+            return 0;
+        }
+        (self.net as f64 / self.hours) as i64
+    }
+}
+
+impl fmt::Display for Profit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let net = Silver::new(self.net.unsigned_abs());
+        let sign = if self.net < 0 { "-" } else { "" };
+        let per_hour = Silver::new(self.net_per_hour().unsigned_abs());
+        write!(
+            f,
+            "{} | expenses {} | net profit {}{} ({}{}/h)",
+            self.gross, self.expenses, sign, net, sign, per_hour
+        )
+    }
+}
+
+#[cfg(test)]
+mod test_expenses {
+    use crate::engine::{
+        ChatParser, ExpenseConfig, ExpenseKind, HourlyCost, LootData, OcrCorrector, Profit,
+        Session, Silver, ValuationContext,
+    };
+
+    #[test]
+    fn chat_expenses() {
+        let parser = ChatParser::default();
+        let corrector = OcrCorrector::default();
+        let config = ExpenseConfig::default();
+        let expense = |line: &str| {
+            parser
+                .parse(&corrector, line)
+                .and_then(|message| config.expense(&message))
+        };
+        let potion =
+            expense("You have bought [HP Potion (Large)]x100 for 250,000 Silver. (16:08)").unwrap();
+        assert_eq!(potion.kind, ExpenseKind::Consumable);
+        assert_eq!(potion.name, "Hp Potion (large)");
+        assert_eq!((potion.amount, potion.silver), (100, 250_000));
+        assert_eq!((potion.hour, potion.minute), (16, 8));
+
+        let bought = expense("You have bought [Black Stone]x1 for 200,000 Silver.").unwrap();
+        assert_eq!(bought.kind, ExpenseKind::Purchase);
+
+        let repair = expense("You have spent 12,500 Silver on repairs. (16:20)").unwrap();
+        assert_eq!(repair.kind, ExpenseKind::Repair);
+        assert_eq!(repair.silver, 12_500);
+
+        assert!(expense("You have obtained [Black Stone]x7.").is_none());
+        assert!(expense("You have sold [Swamp Leaves]x20 for 24,800 Silver.").is_none());
+    }
+
+    #[test]
+    fn net_profit() {
+        let session = Session {
+            started_at: 0,
+            // two hours
+            ended_at: 7_200_000,
+            loot: vec![LootData {
+                name: "Swamp Leaves".to_string(),
+                amount: 1_000,
+                price: Silver::new(1_240),
+                vendor_price: Silver::new(1_240),
+                ..Default::default()
+            }],
+            expenses: vec![
+                ExpenseConfig::default()
+                    .expense(
+                        &ChatParser::default()
+                            .parse(
+                                &OcrCorrector::default(),
+                                "You have spent 40,000 Silver on repairs.",
+                            )
+                            .unwrap(),
+                    )
+                    .unwrap(),
+            ],
+            hourly_costs: vec![HourlyCost {
+                name: "Elixirs".to_string(),
+                silver_per_hour: 100_000,
+            }],
+            ..Default::default()
+        };
+        let profit = Profit::of(&session, &ValuationContext::default());
+        assert_eq!(profit.gross.net_market.value(), 1_240_000);
+        assert_eq!(profit.expenses.value(), 240_000);
+        assert_eq!(profit.net, 1_000_000);
+        assert_eq!(profit.net_per_hour(), 500_000);
+
+        let empty = Session {
+            ended_at: 3_600_000,
+            hourly_costs: session.hourly_costs.clone(),
+            ..Default::default()
+        };
+        let profit = Profit::of(&empty, &ValuationContext::default());
+        assert_eq!(profit.net, -100_000);
+        assert!(profit.to_string().contains("net profit -"));
+    }
+}
@@ -0,0 +1,304 @@
+use std::collections::VecDeque;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::engine::{NameIndex, NameMatch};
+
+pub const GRIND_SPOTS_PATH: &str = "config/grind_spots.json";
+
+// a spot only drops a handful of items, a worse read is still safe to match
+const SPOT_MIN_SCORE: f64 = 0.5;
+// a single drop this many times its usual stack is a misread amount
+const IMPLAUSIBLE_FACTOR: u64 = 10;
+// recent drops the spot is guessed from, common drops excluded
+const DETECTION_WINDOW: usize = 30;
+const MIN_DETECTION_DROPS: usize = 5;
+// part of the window a spot has to explain before it is picked
+const MIN_DETECTION_SHARE: f64 = 0.6;
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExpectedDrop {
+    pub name: String,
+    // picks the right item when several share the name
+    #[serde(default)]
+    pub id: Option<u64>,
+    // usual stack size of a single drop, far bigger amounts are rejected as misreads
+    #[serde(default)]
+    pub typical_amount: Option<u64>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GrindSpot {
+    pub name: String,
+    #[serde(default)]
+    pub trash: Vec<ExpectedDrop>,
+    #[serde(default)]
+    pub rare: Vec<ExpectedDrop>,
+}
+
+impl GrindSpot {
+    pub fn drops(&self) -> impl Iterator<Item = &ExpectedDrop> {
+        self.trash.iter().chain(self.rare.iter())
+    }
+
+    pub fn expected(&self, name: &str) -> Option<&ExpectedDrop> {
+        self.drops()
+            .find(|drop| drop.name.eq_ignore_ascii_case(name.trim()))
+    }
+
+    // true when the drop has no typical amount
+    pub fn plausible_amount(&self, name: &str, amount: u64) -> bool {
+        self.expected(name)
+            .and_then(|drop| drop.typical_amount)
+            .is_none_or(|typical| amount <= typical.max(1).saturating_mul(IMPLAUSIBLE_FACTOR))
+    }
+}
+
+/// Every known grind spot, from `config/grind_spots.json`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GrindSpots {
+    // dropped at every spot, never rejected
+    pub common: Vec<String>,
+    pub spots: Vec<GrindSpot>,
+}
+
+impl Default for GrindSpots {
+    fn default() -> Self {
+        Self {
+            common: vec!["Silver".to_string()],
+            spots: vec![],
+        }
+    }
+}
+
+impl GrindSpots {
+    // a missing file gives no spots
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let data =
+            std::fs::read(path).with_context(|| format!("failed to read {}", path.display()))?;
+        serde_json::from_slice(&data).with_context(|| format!("failed to parse {}", path.display()))
+    }
+
+    pub fn find(&self, name: &str) -> Option<&GrindSpot> {
+        self.spots
+            .iter()
+            .find(|spot| spot.name.eq_ignore_ascii_case(name.trim()))
+    }
+
+    pub fn names(&self) -> Vec<String> {
+        self.spots.iter().map(|spot| spot.name.clone()).collect()
+    }
+}
+
+/// The spot the player is grinding at, ocr names are matched against its drops first.
+#[derive(Clone, Debug)]
+pub struct SpotFilter {
+    pub spot: GrindSpot,
+    // spot drops and common drops
+    index: NameIndex,
+}
+
+impl SpotFilter {
+    pub fn new(spot: GrindSpot, common: &[String]) -> Self {
+        let mut index = NameIndex::from_names(spot.drops().map(|drop| drop.name.as_str()));
+        for name in common {
+            index.insert(name);
+        }
+        index.min_score = SPOT_MIN_SCORE;
+        Self { spot, index }
+    }
+
+    pub fn best_match(&self, name: &str) -> Option<NameMatch> {
+        self.index.best_match(name)
+    }
+
+    // only exact names, call with the canonical name
+    pub fn allows(&self, name: &str) -> bool {
+        self.index
+            .best_match(name)
+            .is_some_and(|found| found.score >= 1.0)
+    }
+
+    pub fn known_drop_ids(&self) -> Vec<u64> {
+        self.spot.drops().filter_map(|drop| drop.id).collect()
+    }
+}
+
+/// Guesses the grind spot from the recent loot, mostly the trash loot name.
+#[derive(Clone, Debug, Default)]
+pub struct SpotDetector {
+    recent: VecDeque<String>,
+    current: Option<String>,
+}
+
+impl SpotDetector {
+    pub fn current(&self) -> Option<&str> {
+        self.current.as_deref()
+    }
+
+    pub fn reset(&mut self) {
+        self.recent.clear();
+        self.current = None;
+    }
+
+    // one name per drop, returns the spot when the guess changed
+    pub fn observe<'a, 'b>(
+        &mut self,
+        spots: &'a GrindSpots,
+        names: impl IntoIterator<Item = &'b str>,
+    ) -> Option<&'a GrindSpot> {
+        for name in names {
+            if spots
+                .common
+                .iter()
+                .any(|c| c.eq_ignore_ascii_case(name.trim()))
+            {
+                continue;
+            }
+            if self.recent.len() == DETECTION_WINDOW {
+                self.recent.pop_front();
+            }
+            self.recent.push_back(name.trim().to_string());
+        }
+        if self.recent.len() < MIN_DETECTION_DROPS {
+            return None;
+        }
+        let mut scores: Vec<(usize, &GrindSpot)> = spots
+            .spots
+            .iter()
+            .map(|spot| {
+                let explained = self
+                    .recent
+                    .iter()
+                    .filter(|name| spot.expected(name).is_some())
+                    .count();
+                (explained, spot)
+            })
+            .collect();
+        scores.sort_by_key(|(explained, _)| std::cmp::Reverse(*explained));
+        let (best, spot) = *scores.first()?;
+        // a tie keeps the current guess
+        if scores.get(1).is_some_and(|(second, _)| *second == best) {
+            return None;
+        }
+        if (best as f64) < self.recent.len() as f64 * MIN_DETECTION_SHARE {
+            return None;
+        }
+        if self.current.as_deref() == Some(spot.name.as_str()) {
+            return None;
+        }
+        self.current = Some(spot.name.clone());
+        Some(spot)
+    }
+}
+
+#[cfg(test)]
+mod test_grind_spot {
+    use crate::engine::{GrindSpots, SpotDetector, SpotFilter};
+
+    fn spots() -> GrindSpots {
+        serde_json::from_str(
+            r#"{
+                "common": ["Silver", "Black Stone"],
+                "spots": [
+                    {
+                        "name": "Swamp Fogans",
+                        "trash": [{ "name": "Swamp Leaves", "typical_amount": 2 }],
+                        "rare": [{ "name": "Fogan Charm", "id": 12345 }]
+                    },
+                    { "name": "Sausans", "trash": [{ "name": "Sausan Insignia" }] }
+                ]
+            }"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn parse_and_find() {
+        let spots = spots();
+        assert_eq!(spots.names(), vec!["Swamp Fogans", "Sausans"]);
+        let fogans = spots.find("swamp fogans").unwrap();
+        assert_eq!(
+            fogans.expected("Swamp Leaves").unwrap().typical_amount,
+            Some(2)
+        );
+        assert!(fogans.expected("Sausan Insignia").is_none());
+        assert!(fogans.plausible_amount("Swamp Leaves", 20));
+        // "x 2" misread as "x 200"
+        assert!(!fogans.plausible_amount("Swamp Leaves", 200));
+        assert!(fogans.plausible_amount("Fogan Charm", 200));
+        assert!(fogans.plausible_amount("Silver", 1_000_000));
+        assert!(spots.find("Moon").is_none());
+        assert_eq!(GrindSpots::default().common, vec!["Silver"]);
+    }
+
+    #[test]
+    fn filter_matches_spot_drops() {
+        let spots = spots();
+        let filter = SpotFilter::new(spots.find("Swamp Fogans").unwrap().clone(), &spots.common);
+        assert_eq!(
+            filter.best_match("SWarnp Leaves").unwrap().name,
+            "Swamp Leaves"
+        );
+        assert_eq!(
+            filter.best_match("8lack St0ne").unwrap().name,
+            "Black Stone"
+        );
+        assert!(filter.allows("Fogan Charm"));
+        assert!(filter.allows("silver"));
+        assert!(!filter.allows("Sausan Insignia"));
+        assert_eq!(filter.known_drop_ids(), vec![12345]);
+    }
+
+    #[test]
+    fn detect_and_switch() {
+        let spots = spots();
+        let mut detector = SpotDetector::default();
+        // silver doesn't count, not enough drops yet
+        let early = ["Silver", "Swamp Leaves", "Silver", "Swamp Leaves"];
+        assert!(detector.observe(&spots, early).is_none());
+        let found = detector.observe(&spots, ["Swamp Leaves", "Fogan Charm", "Swamp Leaves"]);
+        assert_eq!(found.unwrap().name, "Swamp Fogans");
+        // same spot again is not a change
+        assert!(detector.observe(&spots, ["Swamp Leaves"]).is_none());
+
+        // moved on, the new trash loot has to take over most of the window
+        for _ in 0..8 {
+            assert!(detector.observe(&spots, ["Sausan Insignia"]).is_none());
+        }
+        let mut switched = None;
+        for _ in 0..30 {
+            if let Some(spot) = detector.observe(&spots, ["Sausan Insignia"]) {
+                switched = Some(spot.name.clone());
+                break;
+            }
+        }
+        assert_eq!(switched.as_deref(), Some("Sausans"));
+        assert_eq!(detector.current(), Some("Sausans"));
+
+        detector.reset();
+        assert!(detector.current().is_none());
+    }
+
+    #[test]
+    fn unknown_loot_detects_nothing() {
+        let spots = spots();
+        let mut detector = SpotDetector::default();
+        let drops = [
+            "Memory Fragment",
+            "Swamp Leaves",
+            "Memory Fragment",
+            "Ogre Ring",
+            "Caphras Stone",
+        ];
+        assert!(detector.observe(&spots, drops).is_none());
+        assert!(detector.current().is_none());
+    }
+}
@@ -0,0 +1,267 @@
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result};
+use reqwest::{Client, RequestBuilder, Response, StatusCode};
+use tokio::sync::Mutex;
+
+#[derive(Clone, Copy, Debug)]
+pub struct RequestConfig {
+    // sustained request rate
+    pub requests_per_second: f64,
+    // requests allowed at once before the rate kicks in
+    pub burst: u32,
+    // per request, including reading the body
+    pub timeout: Duration,
+    // tries per request on 429, 5xx, timeout and connection errors
+    pub max_attempts: u32,
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+}
+
+impl Default for RequestConfig {
+    fn default() -> Self {
+        Self {
+            requests_per_second: 4.0,
+            burst: 8,
+            timeout: Duration::from_secs(10),
+            max_attempts: 3,
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(8),
+        }
+    }
+}
+
+impl RequestConfig {
+    // exponential backoff with jitter, somewhere between half and the full delay of this attempt
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let delay = self
+            .initial_backoff
+            .saturating_mul(2u32.saturating_pow(attempt.saturating_sub(1)))
+            .min(self.max_backoff);
+        delay.mul_f64(0.5 + jitter() / 2.0)
+    }
+}
+
+// random number in [0, 1), no need for a rng crate for this
+fn jitter() -> f64 {
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u128(
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos(),
+    );
+    (hasher.finish() >> 11) as f64 / (1u64 << 53) as f64
+}
+
+/// Token bucket, waiters are served in order.
+#[derive(Debug)]
+pub struct RateLimiter {
+    rate: f64,
+    capacity: f64,
+    bucket: Mutex<Bucket>,
+}
+
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+impl RateLimiter {
+    pub fn new(requests_per_second: f64, burst: u32) -> Self {
+        let capacity = burst.max(1) as f64;
+        Self {
+            rate: requests_per_second.max(f64::MIN_POSITIVE),
+            capacity,
+            bucket: Mutex::new(Bucket {
+                tokens: capacity,
+                updated: Instant::now(),
+            }),
+        }
+    }
+
+    pub async fn acquire(&self) {
+        // the lock is held while waiting so the next caller queues behind this one
+        let mut bucket = self.bucket.lock().await;
+        loop {
+            let now = Instant::now();
+            let elapsed = now.duration_since(bucket.updated).as_secs_f64();
+            bucket.tokens = (bucket.tokens + elapsed * self.rate).min(self.capacity);
+            bucket.updated = now;
+            if bucket.tokens >= 1.0 {
+                bucket.tokens -= 1.0;
+                return;
+            }
+            tokio::time::sleep(Duration::from_secs_f64((1.0 - bucket.tokens) / self.rate)).await;
+        }
+    }
+}
+
+/// `reqwest::Client` with rate limit, timeout and retry for every request.
+#[derive(Debug, Clone)]
+pub struct HttpClient {
+    client: Client,
+    limiter: Arc<RateLimiter>,
+    pub config: RequestConfig,
+}
+
+impl HttpClient {
+    pub fn new(config: RequestConfig) -> Self {
+        Self {
+            client: Client::new(),
+            limiter: Arc::new(RateLimiter::new(config.requests_per_second, config.burst)),
+            config,
+        }
+    }
+
+    pub async fn get<Q: serde::Serialize + ?Sized>(
+        &self,
+        url: &str,
+        query: &Q,
+    ) -> Result<Response> {
+        self.send(|| self.client.get(url).query(query)).await
+    }
+
+    // `request` is called again for every attempt. non retryable errors return right away
+    pub async fn send(&self, request: impl Fn() -> RequestBuilder) -> Result<Response> {
+        let max_attempts = self.config.max_attempts.max(1);
+        let mut attempt = 0;
+        loop {
+            attempt += 1;
+            self.limiter.acquire().await;
+            let result = request().timeout(self.config.timeout).send().await;
+            let retryable = match &result {
+                Ok(response) => is_retryable(response.status()),
+                Err(err) => err.is_timeout() || err.is_connect(),
+            };
+            if !retryable || attempt >= max_attempts {
+                let response =
+                    result.with_context(|| format!("request failed after {} attempts", attempt))?;
+                return Ok(response.error_for_status()?);
+            }
+            let delay = result
+                .as_ref()
+                .ok()
+                .and_then(retry_after)
+                .unwrap_or_else(|| self.config.backoff(attempt))
+                .min(self.config.max_backoff);
+            tokio::time::sleep(delay).await;
+        }
+    }
+}
+
+fn is_retryable(status: StatusCode) -> bool {
+    status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
+}
+
+// only the seconds form of Retry-After
+fn retry_after(response: &Response) -> Option<Duration> {
+    response
+        .headers()
+        .get(reqwest::header::RETRY_AFTER)?
+        .to_str()
+        .ok()?
+        .trim()
+        .parse()
+        .ok()
+        .map(Duration::from_secs)
+}
+
+#[cfg(test)]
+mod test_http_client {
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::{Duration, Instant};
+
+    use crate::engine::http_client::{HttpClient, RateLimiter, RequestConfig};
+    use crate::engine::stub_server::{StubRequest, StubResponse, StubServer};
+
+    fn config() -> RequestConfig {
+        RequestConfig {
+            requests_per_second: 1000.0,
+            burst: 100,
+            timeout: Duration::from_millis(200),
+            max_attempts: 3,
+            initial_backoff: Duration::from_millis(10),
+            max_backoff: Duration::from_millis(50),
+        }
+    }
+
+    #[test]
+    fn backoff_grows_with_jitter() {
+        let config = config();
+        for attempt in 1..6 {
+            let full = (Duration::from_millis(10) * 2u32.pow(attempt - 1)).min(config.max_backoff);
+            let delay = config.backoff(attempt);
+            assert!(delay >= full / 2 && delay <= full, "{:?}", delay);
+        }
+    }
+
+    #[tokio::test]
+    async fn rate_limit() {
+        let limiter = RateLimiter::new(20.0, 2);
+        let start = Instant::now();
+        for _ in 0..4 {
+            limiter.acquire().await;
+        }
+        // 2 from the burst, then one every 50ms
+        assert!(start.elapsed() >= Duration::from_millis(90));
+    }
+
+    #[tokio::test]
+    async fn retry_server_error() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = calls.clone();
+        let server = StubServer::start(move |_: &StubRequest| {
+            if counter.fetch_add(1, Ordering::SeqCst) < 2 {
+                StubResponse::status(503, "busy")
+            } else {
+                StubResponse::ok("{}")
+            }
+        })
+        .await;
+        let http = HttpClient::new(config());
+        let response = http.get(&server.base_url, &[("q", "x")]).await.unwrap();
+        assert!(response.status().is_success());
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn no_retry_on_client_error() {
+        let server = StubServer::start(|_: &StubRequest| StubResponse::status(404, "")).await;
+        let http = HttpClient::new(config());
+        assert!(http.get(&server.base_url, &()).await.is_err());
+        assert_eq!(server.requests().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn gives_up_on_rate_limit() {
+        let server = StubServer::start(|_: &StubRequest| StubResponse::status(429, "")).await;
+        let http = HttpClient::new(config());
+        let err = http.get(&server.base_url, &()).await.unwrap_err();
+        assert!(err.to_string().contains("429"));
+        assert_eq!(server.requests().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn timeout() {
+        // accepts but never answers
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let hold = tokio::spawn(async move {
+            let mut streams = vec![];
+            while let Ok((stream, _)) = listener.accept().await {
+                streams.push(stream);
+            }
+        });
+        let http = HttpClient::new(config());
+        let start = Instant::now();
+        assert!(http.get(&url, &()).await.is_err());
+        assert!(start.elapsed() >= Duration::from_millis(600));
+        hold.abort();
+    }
+}
@@ -0,0 +1,368 @@
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+
+use anyhow::{Context, Result, anyhow};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use tokio::sync::RwLock;
+
+use crate::engine::item_fetcher::{ItemData, ItemFetcher};
+use crate::engine::{Candidate, Disambiguator, Language};
+
+pub const ITEM_DB_PATH: &str = "config/items.json";
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ItemRecord {
+    pub id: u64,
+    // language code ("en", "kr", ...) to item name
+    pub names: HashMap<String, String>,
+    pub grade: u8,
+    pub weight: f64,
+    pub vendor_buy_price: u64,
+    pub vendor_sell_price: u64,
+    pub category: String,
+    pub has_market_data: bool,
+}
+
+impl ItemRecord {
+    pub fn name(&self, language: &str) -> Option<&str> {
+        self.names
+            .get(language)
+            .or_else(|| self.names.get("en"))
+            .map(String::as_str)
+    }
+}
+
+/// Local copy of the item catalog, so loot can be priced without network.
+#[derive(Default, Debug, Clone)]
+pub struct ItemDb {
+    items: HashMap<u64, ItemRecord>,
+    // lowercase name in any language to ids, some items share a name
+    by_name: HashMap<String, Vec<u64>>,
+}
+
+impl ItemDb {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    pub fn get(&self, id: u64) -> Option<&ItemRecord> {
+        self.items.get(&id)
+    }
+
+    // every item with this name in any language, sorted by id
+    pub fn find_by_name(&self, name: &str) -> Vec<&ItemRecord> {
+        let Some(ids) = self.by_name.get(&name.trim().to_lowercase()) else {
+            return vec![];
+        };
+        let mut out: Vec<&ItemRecord> = ids.iter().filter_map(|id| self.items.get(id)).collect();
+        out.sort_by_key(|item| item.id);
+        out
+    }
+
+    pub fn items(&self) -> impl Iterator<Item = &ItemRecord> {
+        self.items.values()
+    }
+
+    // names in every language, used to build name indexes
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.items
+            .values()
+            .flat_map(|item| item.names.values().map(String::as_str))
+    }
+
+    // names in one language, english where an item has no translation
+    pub fn names_in<'a>(&'a self, language: &'a str) -> impl Iterator<Item = &'a str> {
+        self.items
+            .values()
+            .filter_map(move |item| item.name(language))
+    }
+
+    // insert or update one item. names of languages missing from the update are kept
+    pub fn upsert(&mut self, mut record: ItemRecord) {
+        if let Some(old) = self.items.remove(&record.id) {
+            for (language, name) in old.names.iter() {
+                record
+                    .names
+                    .entry(language.clone())
+                    .or_insert_with(|| name.clone());
+            }
+            self.unindex(&old);
+        }
+        for name in record.names.values() {
+            let ids = self.by_name.entry(name.trim().to_lowercase()).or_default();
+            if !ids.contains(&record.id) {
+                ids.push(record.id);
+            }
+        }
+        self.items.insert(record.id, record);
+    }
+
+    fn unindex(&mut self, record: &ItemRecord) {
+        for name in record.names.values() {
+            let key = name.trim().to_lowercase();
+            if let Some(ids) = self.by_name.get_mut(&key) {
+                ids.retain(|id| *id != record.id);
+                if ids.is_empty() {
+                    self.by_name.remove(&key);
+                }
+            }
+        }
+    }
+
+    // json array of `ItemRecord`, returns amount of imported items
+    pub fn import_json(&mut self, data: &str) -> Result<usize> {
+        let records: Vec<ItemRecord> =
+            serde_json::from_str(data).context("failed to parse item json")?;
+        let count = records.len();
+        for record in records {
+            self.upsert(record);
+        }
+        Ok(count)
+    }
+
+    // csv with a header row. known columns: id, grade, weight, vendor_buy_price,
+    // vendor_sell_price, category, has_market_data, and name_<language> for names
+    pub fn import_csv(&mut self, data: &str) -> Result<usize> {
+        let mut lines = data.lines().filter(|line| !line.trim().is_empty());
+        let header = lines.next().ok_or_else(|| anyhow!("csv is empty"))?;
+        let columns: Vec<String> = split_csv_line(header)
+            .into_iter()
+            .map(|c| c.trim().to_lowercase())
+            .collect();
+        if !columns.iter().any(|c| c == "id") {
+            return Err(anyhow!("csv has no id column"));
+        }
+        let mut count = 0;
+        for (row, line) in lines.enumerate() {
+            let fields = split_csv_line(line);
+            let mut record = ItemRecord::default();
+            for (column, value) in columns.iter().zip(fields.iter()) {
+                let value = value.trim();
+                let parsed = match column.as_str() {
+                    "id" => value.parse().map(|v| record.id = v).is_ok(),
+                    "grade" => value.parse().map(|v| record.grade = v).is_ok(),
+                    "weight" => value.parse().map(|v| record.weight = v).is_ok(),
+                    "vendor_buy_price" => {
+                        value.parse().map(|v| record.vendor_buy_price = v).is_ok()
+                    }
+                    "vendor_sell_price" => {
+                        value.parse().map(|v| record.vendor_sell_price = v).is_ok()
+                    }
+                    "category" => {
+                        record.category = value.to_string();
+                        true
+                    }
+                    "has_market_data" => {
+                        record.has_market_data = matches!(value, "1" | "true" | "TRUE" | "True");
+                        true
+                    }
+                    column => {
+                        if let Some(language) = column.strip_prefix("name_") {
+                            if !value.is_empty() {
+                                record.names.insert(language.to_string(), value.to_string());
+                            }
+                        }
+                        true
+                    }
+                };
+                if !parsed && !value.is_empty() {
+                    return Err(anyhow!("invalid {} {:?} at row {}", column, value, row + 2));
+                }
+            }
+            if record.id == 0 {
+                return Err(anyhow!("missing id at row {}", row + 2));
+            }
+            self.upsert(record);
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    // import a `.json` or `.csv` dump into this db
+    pub async fn import_file(&mut self, path: impl AsRef<Path>) -> Result<usize> {
+        let path = path.as_ref();
+        let data = tokio::fs::read_to_string(path)
+            .await
+            .with_context(|| format!("failed to read {}", path.display()))?;
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("csv") => self.import_csv(&data),
+            _ => self.import_json(&data),
+        }
+    }
+
+    pub async fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let mut db = Self::new();
+        db.import_file(path).await?;
+        Ok(db)
+    }
+
+    pub async fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let mut records: Vec<&ItemRecord> = self.items.values().collect();
+        records.sort_by_key(|item| item.id);
+        let data = serde_json::to_vec_pretty(&records)?;
+        tokio::fs::write(path, data).await?;
+        Ok(())
+    }
+}
+
+fn split_csv_line(line: &str) -> Vec<String> {
+    let mut fields = vec![];
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(std::mem::take(&mut field)),
+            c => field.push(c),
+        }
+    }
+    fields.push(field);
+    fields
+}
+
+/// Prices loot from the local item db, vendor prices only.
+#[derive(Debug, Clone)]
+pub struct ItemDbFetcher {
+    db: Arc<RwLock<ItemDb>>,
+    pub language: Language,
+    pub disambiguator: Arc<Disambiguator>,
+}
+
+impl ItemDbFetcher {
+    pub fn new(db: Arc<RwLock<ItemDb>>) -> Self {
+        Self {
+            db,
+            language: Language::default(),
+            disambiguator: Arc::new(Disambiguator::default()),
+        }
+    }
+
+    pub fn db(&self) -> Arc<RwLock<ItemDb>> {
+        self.db.clone()
+    }
+}
+
+#[async_trait]
+impl ItemFetcher for ItemDbFetcher {
+    async fn get_data_by_name(&self, item_name: &str) -> Result<ItemData> {
+        let db = self.db.read().await;
+        let records = db.find_by_name(item_name);
+        let candidates: Vec<Candidate> = records
+            .iter()
+            .map(|item| Candidate {
+                id: item.id,
+                grade: Some(item.grade as i64),
+                category: Some(item.category.clone()),
+                has_market_data: Some(item.has_market_data),
+            })
+            .collect();
+        let Some((id, recorded)) = self.disambiguator.pick(item_name, &candidates) else {
+            return Err(anyhow!("{} is not in the item db", item_name));
+        };
+        if recorded {
+            if let Err(err) = self.disambiguator.save().await {
+                println!("failed to save item choices: {}", err);
+            }
+        }
+        let item = records.into_iter().find(|item| item.id == id).unwrap();
+        Ok(ItemData {
+            id: item.id,
+            name: item
+                .name(self.language.as_str())
+                .unwrap_or(item_name)
+                .to_string(),
+            vendor_buy_price: item.vendor_buy_price,
+            vendor_sell_price: item.vendor_sell_price,
+            market_buy_price: 0,
+            market_sell_price: 0,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test_item_db {
+    use std::sync::Arc;
+
+    use tokio::sync::RwLock;
+
+    use crate::engine::item_db::{ItemDb, ItemDbFetcher, ItemRecord};
+    use crate::engine::item_fetcher::ItemFetcher;
+
+    const CSV: &str = "id,name_en,name_kr,grade,weight,vendor_buy_price,vendor_sell_price,category,has_market_data
+16001,Black Stone,블랙스톤,0,0.1,0,1000,\"Material, Enhancement\",true
+59820,Narc Magic Mark,,0,0.1,0,12600,Trash Loot,false
+";
+
+    #[test]
+    fn import_csv() {
+        let mut db = ItemDb::new();
+        assert_eq!(db.import_csv(CSV).unwrap(), 2);
+        let stone = db.get(16001).unwrap();
+        assert_eq!(stone.category, "Material, Enhancement");
+        assert!(stone.has_market_data);
+        assert_eq!(stone.name("kr"), Some("블랙스톤"));
+        // fallback to english
+        assert_eq!(db.get(59820).unwrap().name("kr"), Some("Narc Magic Mark"));
+        assert_eq!(db.find_by_name("black stone")[0].id, 16001);
+        assert_eq!(db.find_by_name("블랙스톤")[0].id, 16001);
+        let mut korean: Vec<&str> = db.names_in("kr").collect();
+        korean.sort();
+        assert_eq!(korean, ["Narc Magic Mark", "블랙스톤"]);
+    }
+
+    #[test]
+    fn import_csv_invalid_row() {
+        let mut db = ItemDb::new();
+        assert!(db.import_csv("id,weight\n1,heavy\n").is_err());
+        assert!(db.import_csv("name_en\nSilver\n").is_err());
+    }
+
+    #[test]
+    fn upsert_keeps_other_languages() {
+        let mut db = ItemDb::new();
+        db.import_csv(CSV).unwrap();
+        db.import_json(
+            r#"[{"id":16001,"names":{"en":"Black Stone (Weapon)"},"grade":0,"weight":0.1,
+            "vendor_buy_price":0,"vendor_sell_price":1200,"category":"Material","has_market_data":true}]"#,
+        )
+        .unwrap();
+        assert_eq!(db.len(), 2);
+        let stone = db.get(16001).unwrap();
+        assert_eq!(stone.vendor_sell_price, 1200);
+        assert_eq!(stone.name("kr"), Some("블랙스톤"));
+        assert!(db.find_by_name("Black Stone").is_empty());
+        assert_eq!(db.find_by_name("Black Stone (Weapon)").len(), 1);
+    }
+
+    #[tokio::test]
+    async fn fetch_offline() {
+        let mut db = ItemDb::new();
+        db.import_csv(CSV).unwrap();
+        db.upsert(ItemRecord {
+            id: 44195,
+            names: [("en".to_string(), "Narc Magic Mark".to_string())].into(),
+            ..Default::default()
+        });
+        let fetcher = ItemDbFetcher::new(Arc::new(RwLock::new(db)));
+        let item = fetcher.get_data_by_name("narc magic mark").await.unwrap();
+        assert_eq!(item.id, 59820);
+        assert_eq!(item.name, "Narc Magic Mark");
+        assert_eq!(item.vendor_sell_price, 12600);
+        assert!(fetcher.get_data_by_name("Caphras Stone").await.is_err());
+    }
+}
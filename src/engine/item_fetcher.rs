@@ -1,12 +1,21 @@
 use anyhow::{Context, Ok, Result, anyhow};
 use async_trait::async_trait;
 use chrono::Days;
-use reqwest::Client;
+use futures_util::FutureExt;
+use futures_util::future::{BoxFuture, Shared};
 use serde::{Deserialize, Serialize};
 use serde_json::{Map, Value};
+use std::collections::HashMap;
 use std::fmt;
+use std::sync::{Arc, Mutex};
 
-#[derive(Debug, Clone)]
+use crate::engine::{
+    CachedFetcher, Candidate, ChainFetcher, Disambiguator, HttpClient, ItemDbFetcher, Language,
+    MarketValuation, OverrideFetcher, Region, RequestConfig, StaticPriceFetcher,
+};
+
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct ItemData {
     pub id: u64,
     pub name: String,
@@ -21,28 +30,126 @@ pub trait ItemFetcher: fmt::Debug + Send + Sync {
     async fn get_data_by_name(&self, item_name: &str) -> Result<ItemData>;
 }
 
+/// Where the bdolytics endpoints live, pointed at a local server in tests.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BdolyticsUrls {
+    // search and market analytics
+    pub api: String,
+    // entity detail
+    pub site: String,
+}
+
+impl Default for BdolyticsUrls {
+    fn default() -> Self {
+        Self {
+            api: "https://apiv2.bdolytics.com".to_string(),
+            site: "https://bdolytics.com".to_string(),
+        }
+    }
+}
+
+impl BdolyticsUrls {
+    // every endpoint on the same host
+    pub fn with_base(base: &str) -> Self {
+        let base = base.trim_end_matches('/');
+        Self {
+            api: base.to_string(),
+            site: base.to_string(),
+        }
+    }
+}
+
+type Lookup = Shared<BoxFuture<'static, std::result::Result<ItemData, Arc<anyhow::Error>>>>;
+
+// lookups running right now by lowercase name, a second caller for the same item
+// waits for the first one instead of sending its own request
+#[derive(Clone, Default)]
+struct InFlight(Arc<Mutex<HashMap<String, Lookup>>>);
+
+impl fmt::Debug for InFlight {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let names: Vec<String> = self.0.lock().unwrap().keys().cloned().collect();
+        f.debug_tuple("InFlight").field(&names).finish()
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct DefaultFetcher {
-    client: Client,
-    pub region: String,
+    http: HttpClient,
+    in_flight: InFlight,
+    pub region: Region,
+    pub language: Language,
+    pub valuation: MarketValuation,
+    pub urls: BdolyticsUrls,
+    pub disambiguator: Arc<Disambiguator>,
 }
 
 impl DefaultFetcher {
     pub fn new() -> Self {
+        Self::with_request_config(RequestConfig::default())
+    }
+
+    pub fn with_request_config(config: RequestConfig) -> Self {
         Self {
-            client: Client::new(),
-            region: "SEA".to_string(),
+            http: HttpClient::new(config),
+            in_flight: InFlight::default(),
+            region: Region::default(),
+            language: Language::default(),
+            valuation: MarketValuation::default(),
+            urls: BdolyticsUrls::default(),
+            disambiguator: Arc::new(Disambiguator::default()),
         }
     }
+
+    async fn fetch(&self, item_name: &str) -> Result<ItemData> {
+        let id = search_id_by_name(
+            &self.http,
+            &self.urls,
+            self.region,
+            self.language,
+            &self.disambiguator,
+            item_name,
+        )
+        .await
+        .context("failed to search id by name")?;
+        let detail = item_detail(&self.http, &self.urls, self.region, self.language, id)
+            .await
+            .context("failed to find item detail")?;
+
+        let mut item_data = ItemData {
+            id,
+            name: detail.name,
+            vendor_buy_price: detail.buy_price,
+            vendor_sell_price: detail.sell_price,
+            market_buy_price: 0,
+            market_sell_price: 0,
+        };
+        if detail.has_market_data {
+            let item_market_data = market_data(&self.http, &self.urls, self.region, id, 0)
+                .await
+                .context("failed to get item market data")?;
+            // no usable trade point leaves the market price at 0, the vendor price is used instead
+            let (buy, sell) = self.valuation.evaluate(&item_market_data);
+            item_data.market_buy_price = buy.unwrap_or_default();
+            item_data.market_sell_price = sell.unwrap_or_default();
+        }
+        Ok(item_data)
+    }
 }
 
-async fn search_id_by_name(client: &Client, name: &str) -> Result<u64> {
-    let response = client
-        .get(format!(
-            "https://apiv2.bdolytics.com/en/SEA/db/query-extended?q={}",
-            name
-        ))
-        .send()
+async fn search_id_by_name(
+    http: &HttpClient,
+    urls: &BdolyticsUrls,
+    region: Region,
+    language: Language,
+    disambiguator: &Disambiguator,
+    name: &str,
+) -> Result<u64> {
+    let response = http
+        .get(
+            &format!("{}/{}/{}/db/query-extended", urls.api, language, region),
+            &[("q", name)],
+        )
         .await?;
     // println!("{}", response.text().await?);
     // return Ok(1);
@@ -55,32 +162,42 @@ async fn search_id_by_name(client: &Client, name: &str) -> Result<u64> {
     if !search_result.status.success {
         return Err(anyhow!("API request was not successful"));
     }
-    let mut data = search_result.data;
-    data = data
+    let candidates: Vec<Candidate> = search_result
+        .data
         .into_iter()
         .filter(|i| i.db_type == "item" && i.name.eq_ignore_ascii_case(name))
+        .map(|i| Candidate {
+            id: i.id,
+            grade: i.grade_type,
+            ..Default::default()
+        })
         .collect();
-    data.sort_by_key(|item| item.id);
-    if data.len() == 0 {
+    let Some((id, recorded)) = disambiguator.pick(name, &candidates) else {
         return Err(anyhow!("data doesnt exist"));
+    };
+    if recorded {
+        if let Err(err) = disambiguator.save().await {
+            println!("failed to save item choices: {}", err);
+        }
     }
-    // low to high.
-    // new trashloot always have higher id
-    // the other base item usually have lower id
-    let mut item = data.first().unwrap();
-    if item.grade_type.unwrap() == 0 {
-        item = data.last().unwrap();
-    }
-    Ok(item.id)
+    Ok(id)
 }
 async fn item_detail(
-    client: &Client,
-    region: &str,
+    http: &HttpClient,
+    urls: &BdolyticsUrls,
+    region: Region,
+    language: Language,
     item_id: u64,
 ) -> Result<BdolyticsItemDetailResultData> {
-    let response = client
-        .get(format!("https://bdolytics.com/api/trpc/database.getEntity?input={{\"id\":{},\"dbType\":\"item\",\"region\":\"{}\",\"language\":\"en\"}}", item_id,region))
-        .send()
+    let input = format!(
+        "{{\"id\":{},\"dbType\":\"item\",\"region\":\"{}\",\"language\":\"{}\"}}",
+        item_id, region, language
+    );
+    let response = http
+        .get(
+            &format!("{}/api/trpc/database.getEntity", urls.site),
+            &[("input", input)],
+        )
         .await?;
     // println!("{}", response.text().await?);
     // return Ok(1);
@@ -95,16 +212,24 @@ async fn item_detail(
 }
 
 async fn market_data(
-    client: &Client,
-    region: &str,
+    http: &HttpClient,
+    urls: &BdolyticsUrls,
+    region: Region,
     item_id: u64,
     enhancement_level: u8,
 ) -> Result<Vec<Trade>> {
     let now = chrono::Utc::now();
     let start_date = now.checked_sub_days(Days::new(1)).unwrap();
-    let response = client
-        .get(format!("https://apiv2.bdolytics.com/market/analytics/{}?start_date={}&end_date={}&region={}&enhancement_level={}", item_id,start_date.timestamp_millis(),now.timestamp_millis(),region, enhancement_level))
-        .send()
+    let response = http
+        .get(
+            &format!("{}/market/analytics/{}", urls.api, item_id),
+            &[
+                ("start_date", start_date.timestamp_millis().to_string()),
+                ("end_date", now.timestamp_millis().to_string()),
+                ("region", region.to_string()),
+                ("enhancement_level", enhancement_level.to_string()),
+            ],
+        )
         .await?;
 
     let market_detail = response.json::<BdolyticsMarketAnalytics>().await?;
@@ -118,48 +243,41 @@ async fn market_data(
 #[async_trait]
 impl ItemFetcher for DefaultFetcher {
     async fn get_data_by_name(&self, item_name: &str) -> Result<ItemData> {
-        if item_name.contains("event") {
-            return Ok(ItemData {
-                id: 0,
-                name: item_name.to_string(),
-                vendor_buy_price: 0,
-                vendor_sell_price: 0,
-                market_buy_price: 0,
-                market_sell_price: 0,
-            });
-        }
-        let id = search_id_by_name(&self.client, item_name)
-            .await
-            .context("failed to search id by name")?;
-        let detail = item_detail(&self.client, &self.region, id)
-            .await
-            .context("failed to find item detail")?;
-
-        let mut item_data = ItemData {
-            id,
-            name: detail.name,
-            vendor_buy_price: detail.buy_price,
-            vendor_sell_price: detail.sell_price,
-            market_buy_price: 0,
-            market_sell_price: 0,
+        let key = item_name.trim().to_lowercase();
+        let lookup = {
+            let mut in_flight = self.in_flight.0.lock().unwrap();
+            in_flight
+                .entry(key.clone())
+                .or_insert_with(|| {
+                    let fetcher = self.clone();
+                    let item_name = item_name.to_string();
+                    async move { fetcher.fetch(&item_name).await.map_err(Arc::new) }
+                        .boxed()
+                        .shared()
+                })
+                .clone()
         };
-        if item_name == "Silver" {
-            item_data.vendor_sell_price = 1
-        }
-        if detail.has_market_data {
-            let item_market_data = market_data(&self.client, &self.region, id, 0)
-                .await
-                .context("failed to get item market data")?;
-            item_data.market_buy_price = item_market_data[0].price;
-            item_data.market_sell_price = item_market_data[0].price;
+        let result = lookup.clone().await;
+        {
+            // the first one to finish removes it, unless a newer lookup already took the slot
+            let mut in_flight = self.in_flight.0.lock().unwrap();
+            if in_flight.get(&key).is_some_and(|l| l.ptr_eq(&lookup)) {
+                in_flight.remove(&key);
+            }
         }
-        Ok(item_data)
+        result.map_err(|err| anyhow!("{:#}", err))
     }
 }
 
 #[derive(Debug)]
 pub enum Fetcher {
     Default(DefaultFetcher),
+    ItemDb(ItemDbFetcher),
+    Cached(CachedFetcher<Fetcher>),
+    Static(StaticPriceFetcher),
+    Overrides(OverrideFetcher),
+    Chain(ChainFetcher),
+    Custom(Box<dyn ItemFetcher>),
     // Add other fetcher variants here as needed
 }
 
@@ -176,6 +294,12 @@ impl ItemFetcher for Fetcher {
     async fn get_data_by_name(&self, item_name: &str) -> Result<ItemData> {
         match self {
             Fetcher::Default(fetcher) => fetcher.get_data_by_name(item_name).await,
+            Fetcher::ItemDb(fetcher) => fetcher.get_data_by_name(item_name).await,
+            Fetcher::Cached(fetcher) => fetcher.get_data_by_name(item_name).await,
+            Fetcher::Static(fetcher) => fetcher.get_data_by_name(item_name).await,
+            Fetcher::Overrides(fetcher) => fetcher.get_data_by_name(item_name).await,
+            Fetcher::Chain(fetcher) => fetcher.get_data_by_name(item_name).await,
+            Fetcher::Custom(fetcher) => fetcher.get_data_by_name(item_name).await,
         }
     }
 }
@@ -202,6 +326,8 @@ pub struct Status {
 
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct BdolyticsItemDetailOut {
+    // missing when the request failed
+    #[serde(default)]
     pub result: BdolyticsItemDetailResult,
     pub error: Option<Map<String, Value>>,
 }
@@ -236,12 +362,13 @@ pub struct BdolyticsMarketAnalytics {
     pub data: Vec<Trade>,
     pub error: Option<Map<String, Value>>,
 }
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Trade {
-    id: u64,
-    price: u64,
-    volume: u64,
-    stock: u64,
+    // unix millis
+    pub time: u64,
+    pub price: u64,
+    pub volume: u64,
+    pub stock: u64,
 }
 
 #[derive(Debug, Deserialize)]
@@ -250,7 +377,7 @@ struct TradeRaw(u64, u64, u64, u64);
 impl From<TradeRaw> for Trade {
     fn from(raw: TradeRaw) -> Self {
         Trade {
-            id: raw.0,
+            time: raw.0,
             price: raw.1,
             volume: raw.2,
             stock: raw.3,
@@ -269,24 +396,208 @@ impl<'de> Deserialize<'de> for Trade {
 
 #[cfg(test)]
 mod test_bdolytics {
-    use reqwest::Client;
+    use std::time::Duration;
+
+    use crate::engine::item_fetcher::{
+        BdolyticsUrls, DefaultFetcher, ItemFetcher, item_detail, market_data, search_id_by_name,
+    };
+    use crate::engine::stub_server::{StubRequest, StubResponse, StubServer, fixture};
+    use crate::engine::{Disambiguator, HttpClient, Language, Region, RequestConfig};
+
+    // hand written answers in the shape of the bdolytics api, not captured responses.
+    // `analytics` is the fixture used for the market data
+    fn bdolytics(analytics: &'static str) -> impl Fn(&StubRequest) -> StubResponse {
+        move |req| match req.path.as_str() {
+            "/en/SEA/db/query-extended" => match req.query.get("q").map(String::as_str) {
+                Some("Narc Magic Mark") => StubResponse::ok(fixture("search_narc_magic_mark.json")),
+                Some("Caphras Stone") => StubResponse::ok(fixture("search_caphras_stone.json")),
+                Some("Broken Search") => StubResponse::ok(fixture("search_error.json")),
+                _ => StubResponse::ok(r#"{"data": [], "status": {"success": true}}"#),
+            },
+            "/api/trpc/database.getEntity" => {
+                let input = req.query.get("input").cloned().unwrap_or_default();
+                if input.contains("\"id\":721003") {
+                    StubResponse::ok(fixture("entity_721003.json"))
+                } else if input.contains("\"id\":59820") {
+                    StubResponse::ok(fixture("entity_59820.json"))
+                } else {
+                    StubResponse::ok(fixture("entity_error.json"))
+                }
+            }
+            "/market/analytics/721003" => StubResponse::ok(fixture(analytics)),
+            _ => StubResponse::status(404, "not found"),
+        }
+    }
+
+    fn config() -> RequestConfig {
+        RequestConfig {
+            initial_backoff: Duration::from_millis(10),
+            ..Default::default()
+        }
+    }
 
-    use crate::engine::item_fetcher::{item_detail, market_data, search_id_by_name};
+    fn http() -> HttpClient {
+        HttpClient::new(config())
+    }
+
+    fn fetcher(server: &StubServer) -> DefaultFetcher {
+        let mut fetcher = DefaultFetcher::with_request_config(config());
+        fetcher.urls = BdolyticsUrls::with_base(&server.base_url);
+        fetcher
+    }
 
     #[tokio::test]
     async fn test_search_item_by_name() {
-        let client = Client::new();
-        let result = search_id_by_name(&client, "Narc Magic Mark").await.unwrap();
+        let server = StubServer::start(bdolytics("analytics.json")).await;
+        let urls = BdolyticsUrls::with_base(&server.base_url);
+        let client = http();
+        // the trash loot, not the older item with the same name
+        let result = search_id_by_name(
+            &client,
+            &urls,
+            Region::SEA,
+            Language::En,
+            &Disambiguator::default(),
+            "Narc Magic Mark",
+        )
+        .await
+        .unwrap();
         assert_eq!(result, 59820);
+        assert!(server.requests()[0].contains("q=Narc"));
     }
     #[tokio::test]
     async fn test_search_item_detail() {
-        let client = Client::new();
-        let result = search_id_by_name(&client, "Caphras Stone").await.unwrap();
-        let detail = item_detail(&client, "SEA", result).await.unwrap();
-        let market_data = market_data(&client, "SEA", result, 0).await.unwrap();
+        let server = StubServer::start(bdolytics("analytics.json")).await;
+        let urls = BdolyticsUrls::with_base(&server.base_url);
+        let client = http();
+        let result = search_id_by_name(
+            &client,
+            &urls,
+            Region::SEA,
+            Language::En,
+            &Disambiguator::default(),
+            "Caphras Stone",
+        )
+        .await
+        .unwrap();
+        let detail = item_detail(&client, &urls, Region::SEA, Language::En, result)
+            .await
+            .unwrap();
+        let market_data = market_data(&client, &urls, Region::SEA, result, 0)
+            .await
+            .unwrap();
         assert_eq!(result, 721003);
         assert_eq!(detail.has_market_data, true);
         assert!(!market_data.is_empty());
+        assert!(server.requests()[2].contains("region=SEA"));
+    }
+
+    #[tokio::test]
+    async fn fetch_market_item() {
+        let server = StubServer::start(bdolytics("analytics.json")).await;
+        let item = fetcher(&server)
+            .get_data_by_name("Caphras Stone")
+            .await
+            .unwrap();
+        assert_eq!(item.id, 721003);
+        assert_eq!(item.vendor_sell_price, 100_000);
+        assert_eq!(item.market_buy_price, 2_490_000);
+        assert_eq!(item.market_sell_price, 2_480_000);
+    }
+
+    #[tokio::test]
+    async fn fetch_vendor_item() {
+        let server = StubServer::start(bdolytics("analytics.json")).await;
+        let item = fetcher(&server)
+            .get_data_by_name("Narc Magic Mark")
+            .await
+            .unwrap();
+        assert_eq!(item.vendor_sell_price, 12_600);
+        assert_eq!(item.market_sell_price, 0);
+        // no market data, analytics is never asked
+        assert_eq!(server.requests().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn empty_market_data() {
+        let server = StubServer::start(bdolytics("analytics_empty.json")).await;
+        let item = fetcher(&server)
+            .get_data_by_name("Caphras Stone")
+            .await
+            .unwrap();
+        assert_eq!(item.market_sell_price, 0);
+        assert_eq!(item.vendor_sell_price, 100_000);
+    }
+
+    #[tokio::test]
+    async fn error_payloads() {
+        let server = StubServer::start(bdolytics("analytics_error.json")).await;
+        let fetcher = fetcher(&server);
+        assert!(fetcher.get_data_by_name("Broken Search").await.is_err());
+        assert!(fetcher.get_data_by_name("Caphras Stone").await.is_err());
+        assert!(fetcher.get_data_by_name("Swamp Leaves").await.is_err());
+
+        let client = http();
+        let urls = BdolyticsUrls::with_base(&server.base_url);
+        assert!(
+            item_detail(&client, &urls, Region::SEA, Language::En, 1)
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn http_error() {
+        let server = StubServer::start(|_: &StubRequest| StubResponse::status(503, "down")).await;
+        let err = fetcher(&server)
+            .get_data_by_name("Caphras Stone")
+            .await
+            .unwrap_err();
+        assert!(format!("{:#}", err).contains("503"));
+    }
+
+    #[tokio::test]
+    async fn coalesce_same_item() {
+        let server = StubServer::start(bdolytics("analytics.json")).await;
+        let fetcher = fetcher(&server);
+        let lookups = (0..5).map(|i| {
+            let fetcher = fetcher.clone();
+            async move {
+                let name = if i % 2 == 0 {
+                    "Caphras Stone"
+                } else {
+                    "caphras stone"
+                };
+                fetcher.get_data_by_name(name).await.unwrap()
+            }
+        });
+        let items = futures_util::future::join_all(lookups).await;
+        assert!(items.iter().all(|item| item.id == 721003));
+        // search, detail and analytics once
+        assert_eq!(server.requests().len(), 3);
+        assert!(fetcher.in_flight.0.lock().unwrap().is_empty());
+        // finished lookups are not cached here
+        fetcher.get_data_by_name("Caphras Stone").await.unwrap();
+        assert_eq!(server.requests().len(), 6);
+    }
+
+    #[tokio::test]
+    async fn region_and_language() {
+        let server = StubServer::start(|req: &StubRequest| match req.path.as_str() {
+            "/kr/KR/db/query-extended" => StubResponse::ok(fixture("search_caphras_stone.json")),
+            "/api/trpc/database.getEntity" => StubResponse::ok(fixture("entity_721003.json")),
+            "/market/analytics/721003" if req.query.get("region").unwrap() == "KR" => {
+                StubResponse::ok(fixture("analytics.json"))
+            }
+            _ => StubResponse::status(404, "not found"),
+        })
+        .await;
+        let mut fetcher = fetcher(&server);
+        fetcher.region = Region::KR;
+        fetcher.language = Language::Kr;
+        fetcher.get_data_by_name("Caphras Stone").await.unwrap();
+        let requests = server.requests();
+        assert!(requests[1].contains("%22region%22%3A%22KR%22"));
+        assert!(requests[1].contains("%22language%22%3A%22kr%22"));
     }
 }
@@ -0,0 +1,451 @@
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::engine::{ChatEventKind, Language, MessageTemplate, NumberFormat};
+
+pub const LOCALES_PATH: &str = "config/locales.json";
+
+/// Chat messages and number format of one client language.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LocalePack {
+    pub language: Language,
+    #[serde(default)]
+    pub number_format: NumberFormat,
+    // tried in order, the first match wins
+    pub messages: Vec<MessageTemplate>,
+}
+
+fn messages(list: &[(ChatEventKind, &str, &str)]) -> Vec<MessageTemplate> {
+    list.iter()
+        .map(|(kind, prefix, body)| MessageTemplate {
+            kind: *kind,
+            prefix: prefix.to_string(),
+            body: body.to_string(),
+        })
+        .collect()
+}
+
+impl LocalePack {
+    fn new(language: Language, list: &[(ChatEventKind, &str, &str)]) -> Self {
+        Self {
+            language,
+            number_format: NumberFormat::for_language(language),
+            messages: messages(list),
+        }
+    }
+
+    pub fn english() -> Self {
+        use ChatEventKind::*;
+        Self::new(
+            Language::En,
+            &[
+                // [Swamp Leaves]x2 has been moved to the Family Inventory.
+                (
+                    FamilyInventory,
+                    "",
+                    r"{item}\s+(?:has\s+been\s+)?(?:moved|added|placed)\s+(?:in)?to\s+(?:the\s+|your\s+)?family\s+inventory[\s.]*",
+                ),
+                // You have sold [Swamp Leaves]x2 for 1,240 Silver.
+                (
+                    SoldToNpc,
+                    "you have sold",
+                    r"{item}\s+for\s+{silver}\s*silver[\s.]*",
+                ),
+                // You have bought [HP Potion (Large)]x100 for 250,000 Silver.
+                (
+                    Purchased,
+                    "you have bought",
+                    r"{item}\s+for\s+{silver}\s*silver[\s.]*",
+                ),
+                // You have lost [Black Stone]x1.
+                (Lost, "you have lost", "{item}"),
+                // You have obtained [Black Stone]x7.
+                (Obtained, "you have obtained", "{item}"),
+                // You have deposited 1,234,567 Silver into the storage.
+                (
+                    Deposited,
+                    "you have deposited",
+                    r"{silver}\s*silver\b[^()]*?",
+                ),
+                // You have spent 12,500 Silver on repairs.
+                (
+                    Repaired,
+                    "you have spent",
+                    r"{silver}\s*silver\s+(?:on|for)\s+repairs?[\s.]*",
+                ),
+            ],
+        )
+    }
+
+    pub fn korean() -> Self {
+        use ChatEventKind::*;
+        // particles are written as 을(를) when the client doesn't pick one
+        Self::new(
+            Language::Kr,
+            &[
+                // [늪지 잎사귀]x2이(가) 가문 인벤토리로 이동되었습니다.
+                (
+                    FamilyInventory,
+                    "",
+                    r"{item}\s*(?:이\(가\)|이|가)?\s*가문\s*인벤토리[^()]*?",
+                ),
+                // [늪지 잎사귀]x20을(를) 24,800 은화에 판매했습니다.
+                (
+                    SoldToNpc,
+                    "",
+                    r"{item}\s*(?:을\(를\)|을|를)?\s*{silver}\s*은화에?\s*판매[^()]*?",
+                ),
+                // [HP 회복 물약 (대)]x100을(를) 250,000 은화에 구매했습니다.
+                (
+                    Purchased,
+                    "",
+                    r"{item}\s*(?:을\(를\)|을|를)?\s*{silver}\s*은화에?\s*구매[^()]*?",
+                ),
+                // [블랙스톤]x1을(를) 잃었습니다.
+                (Lost, "", r"{item}\s*(?:을\(를\)|을|를)?\s*잃[^()]*?"),
+                // [블랙스톤]x7을(를) 획득했습니다.
+                (Obtained, "", r"{item}\s*(?:을\(를\)|을|를)?\s*획득[^()]*?"),
+                // 1,234,567 은화를 창고에 맡겼습니다.
+                (Deposited, "", r"{silver}\s*은화[^()]*?창고[^()]*?"),
+                // 수리 비용으로 12,500 은화를 사용했습니다.
+                (Repaired, "수리 비용으로", r"{silver}\s*은화[^()]*?"),
+            ],
+        )
+    }
+
+    pub fn spanish() -> Self {
+        use ChatEventKind::*;
+        Self::new(
+            Language::Es,
+            &[
+                // [Hojas de pantano]x2 se ha movido al inventario familiar.
+                (
+                    FamilyInventory,
+                    "",
+                    r"{item}\s+(?:se\s+ha\s+)?(?:movido|añadido|enviado)\s+al\s+inventario\s+(?:familiar|de\s+la\s+familia)[\s.]*",
+                ),
+                // Has vendido [Hojas de pantano]x20 por 24.800 de plata.
+                (
+                    SoldToNpc,
+                    "has vendido",
+                    r"{item}\s+por\s+{silver}\s*(?:de\s+)?plata[\s.]*",
+                ),
+                (
+                    Purchased,
+                    "has comprado",
+                    r"{item}\s+por\s+{silver}\s*(?:de\s+)?plata[\s.]*",
+                ),
+                (Lost, "has perdido", "{item}"),
+                // Has obtenido [Piedra negra]x7.
+                (Obtained, "has obtenido", "{item}"),
+                (
+                    Deposited,
+                    "has depositado",
+                    r"{silver}\s*(?:de\s+)?plata\b[^()]*?",
+                ),
+                (
+                    Repaired,
+                    "has gastado",
+                    r"{silver}\s*(?:de\s+)?plata\s+en\s+reparaci(?:o|ó)n(?:es)?[\s.]*",
+                ),
+            ],
+        )
+    }
+
+    pub fn portuguese() -> Self {
+        use ChatEventKind::*;
+        Self::new(
+            Language::Pt,
+            &[
+                // [Folhas do Pântano]x2 foi movido para o Inventário da Família.
+                (
+                    FamilyInventory,
+                    "",
+                    r"{item}\s+(?:foi|foram)\s+(?:movid|adicionad|enviad)[oa]s?\s+(?:para|ao)\s+(?:o\s+)?invent(?:a|á)rio\s+(?:da\s+)?fam(?:i|í)lia[\s.]*",
+                ),
+                // Você vendeu [Folhas do Pântano]x20 por 24.800 Prata.
+                (
+                    SoldToNpc,
+                    "você vendeu",
+                    r"{item}\s+por\s+{silver}\s*(?:de\s+)?prata[\s.]*",
+                ),
+                (
+                    Purchased,
+                    "você comprou",
+                    r"{item}\s+por\s+{silver}\s*(?:de\s+)?prata[\s.]*",
+                ),
+                (Lost, "você perdeu", "{item}"),
+                // Você obteve [Pedra Negra]x7.
+                (Obtained, "você obteve", "{item}"),
+                (
+                    Deposited,
+                    "você depositou",
+                    r"{silver}\s*(?:de\s+)?prata\b[^()]*?",
+                ),
+                (
+                    Repaired,
+                    "você gastou",
+                    r"{silver}\s*(?:de\s+)?prata\s+(?:em|com)\s+reparos?[\s.]*",
+                ),
+            ],
+        )
+    }
+
+    pub fn french() -> Self {
+        use ChatEventKind::*;
+        Self::new(
+            Language::Fr,
+            &[
+                // [Feuilles de marais]x2 a été déplacé dans l'inventaire familial.
+                (
+                    FamilyInventory,
+                    "",
+                    r"{item}\s+(?:a|ont)\s+(?:été|ete)\s+(?:déplacée?s?|deplacee?s?|ajoutée?s?|ajoutee?s?)\s+(?:dans|à|a)\s+l'?\s*inventaire\s+familial[\s.]*",
+                ),
+                // Vous avez vendu [Feuilles de marais]x20 pour 24 800 Argent.
+                (
+                    SoldToNpc,
+                    "vous avez vendu",
+                    r"{item}\s+pour\s+{silver}\s*(?:d')?argent[\s.]*",
+                ),
+                (
+                    Purchased,
+                    "vous avez acheté",
+                    r"{item}\s+pour\s+{silver}\s*(?:d')?argent[\s.]*",
+                ),
+                (Lost, "vous avez perdu", "{item}"),
+                // Vous avez obtenu [Pierre noire]x7.
+                (Obtained, "vous avez obtenu", "{item}"),
+                (
+                    Deposited,
+                    "vous avez déposé",
+                    r"{silver}\s*(?:d')?argent\b[^()]*?",
+                ),
+                (
+                    Repaired,
+                    "vous avez dépensé",
+                    r"{silver}\s*(?:d')?argent\s+(?:en|pour\s+les)\s+réparations?[\s.]*",
+                ),
+            ],
+        )
+    }
+
+    pub fn german() -> Self {
+        use ChatEventKind::*;
+        // the verb comes last, every message starts with "ihr habt"
+        Self::new(
+            Language::De,
+            &[
+                // [Sumpfblätter]x2 wurde ins Familieninventar verschoben.
+                (
+                    FamilyInventory,
+                    "",
+                    r"{item}\s+(?:wurde|wurden)\s+(?:ins|in\s+das)\s+familieninventar\s+(?:verschoben|gelegt|hinzugefügt)[\s.]*",
+                ),
+                // Ihr habt [Sumpfblätter]x20 für 24.800 Silber verkauft.
+                (
+                    SoldToNpc,
+                    "ihr habt",
+                    r"{item}\s+für\s+{silver}\s*silber\s+verkauft[\s.]*",
+                ),
+                (
+                    Purchased,
+                    "ihr habt",
+                    r"{item}\s+für\s+{silver}\s*silber\s+gekauft[\s.]*",
+                ),
+                (Lost, "ihr habt", r"{item}\s*verloren[\s.]*"),
+                // Ihr habt [Schwarzstein]x7 erhalten.
+                (Obtained, "ihr habt", r"{item}\s*erhalten[\s.]*"),
+                (
+                    Deposited,
+                    "ihr habt",
+                    r"{silver}\s*silber\s+(?:im|ins|in\s+das)\s+lager\s+eingelagert[\s.]*",
+                ),
+                (
+                    Repaired,
+                    "ihr habt",
+                    r"{silver}\s*silber\s+für\s+(?:die\s+)?reparaturen?\s+ausgegeben[\s.]*",
+                ),
+            ],
+        )
+    }
+}
+
+/// Built in packs, a pack for the same language in `config/locales.json` replaces one.
+#[derive(Clone, Debug)]
+pub struct LocalePacks {
+    packs: Vec<LocalePack>,
+}
+
+impl Default for LocalePacks {
+    fn default() -> Self {
+        Self {
+            packs: vec![
+                LocalePack::english(),
+                LocalePack::korean(),
+                LocalePack::spanish(),
+                LocalePack::portuguese(),
+                LocalePack::french(),
+                LocalePack::german(),
+            ],
+        }
+    }
+}
+
+impl LocalePacks {
+    // a missing file gives the built in packs
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let mut packs = Self::default();
+        if !path.exists() {
+            return Ok(packs);
+        }
+        let data =
+            std::fs::read(path).with_context(|| format!("failed to read {}", path.display()))?;
+        let custom: Vec<LocalePack> = serde_json::from_slice(&data)
+            .with_context(|| format!("failed to parse {}", path.display()))?;
+        for pack in custom {
+            packs.insert(pack);
+        }
+        Ok(packs)
+    }
+
+    pub fn insert(&mut self, pack: LocalePack) {
+        match self.packs.iter_mut().find(|p| p.language == pack.language) {
+            Some(old) => *old = pack,
+            None => self.packs.push(pack),
+        }
+    }
+
+    // languages without a pack read english chat
+    pub fn get(&self, language: Language) -> LocalePack {
+        self.packs
+            .iter()
+            .find(|pack| pack.language == language)
+            .cloned()
+            .unwrap_or_else(|| LocalePack {
+                language,
+                number_format: NumberFormat::for_language(language),
+                ..LocalePack::english()
+            })
+    }
+
+    pub fn languages(&self) -> Vec<Language> {
+        self.packs.iter().map(|pack| pack.language).collect()
+    }
+}
+
+#[cfg(test)]
+mod test_locale {
+    use crate::engine::{
+        ChatEvent, ChatLoot, ChatParser, Language, LocalePack, LocalePacks, OcrCorrector,
+    };
+
+    fn loot(name: &str, amount: u64) -> ChatLoot {
+        ChatLoot {
+            name: name.to_string(),
+            amount,
+        }
+    }
+
+    fn parse(pack: LocalePack, line: &str) -> Option<ChatEvent> {
+        let mut corrector = OcrCorrector::default();
+        corrector.number_format = pack.number_format.clone();
+        let parser = ChatParser::new(&pack).unwrap();
+        parser.parse(&corrector, line).map(|message| message.event)
+    }
+
+    #[test]
+    fn built_in_packs() {
+        let cases = [
+            (
+                LocalePack::korean(),
+                "[블랙스톤]x7을(를) 획득했습니다. (16:08)",
+                Some(ChatEvent::Obtained(loot("블랙스톤", 7))),
+            ),
+            (
+                LocalePack::korean(),
+                "수리 비용으로 12,500 은화를 사용했습니다.",
+                Some(ChatEvent::Repaired { silver: 12_500 }),
+            ),
+            (
+                LocalePack::spanish(),
+                "Has obtenido [Piedra negra]x7. (16:08)",
+                Some(ChatEvent::Obtained(loot("Piedra Negra", 7))),
+            ),
+            (
+                LocalePack::portuguese(),
+                "Você vendeu [Pedra Negra]x20 por 24.800 Prata.",
+                Some(ChatEvent::SoldToNpc {
+                    loot: loot("Pedra Negra", 20),
+                    silver: 24_800,
+                }),
+            ),
+            (
+                LocalePack::french(),
+                "Vous avez déposé 1 234 567 Argent dans l'entrepôt.",
+                Some(ChatEvent::Deposited { silver: 1_234_567 }),
+            ),
+            (
+                LocalePack::german(),
+                "Ihr habt [Schwarzstein]x7 erhalten.",
+                Some(ChatEvent::Obtained(loot("Schwarzstein", 7))),
+            ),
+            (
+                LocalePack::german(),
+                "Ihr habt [Schwarzstein]x1 verloren.",
+                Some(ChatEvent::Lost(loot("Schwarzstein", 1))),
+            ),
+            // the decimal comma matters for suffixed amounts
+            (
+                LocalePack::german(),
+                "Ihr habt 1,5M Silber im Lager eingelagert.",
+                Some(ChatEvent::Deposited { silver: 1_500_000 }),
+            ),
+            // english lines in another language don't match
+            (
+                LocalePack::german(),
+                "You have obtained [Black Stone]x7.",
+                None,
+            ),
+        ];
+        for (pack, line, expected) in cases {
+            assert_eq!(parse(pack, line), expected, "{:?}", line);
+        }
+    }
+
+    #[test]
+    fn custom_pack_replaces_built_in() {
+        let custom: Vec<LocalePack> = serde_json::from_str(
+            r#"[{
+                "language": "en",
+                "messages": [{ "kind": "obtained", "prefix": "looted", "body": "{item}" }]
+            }]"#,
+        )
+        .unwrap();
+        let mut packs = LocalePacks::default();
+        for pack in custom {
+            packs.insert(pack);
+        }
+        let english = packs.get(Language::En);
+        assert_eq!(
+            parse(english.clone(), "Looted [Black Stone]x2"),
+            Some(ChatEvent::Obtained(loot("Black Stone", 2)))
+        );
+        assert_eq!(parse(english, "You have obtained [Black Stone]x2"), None);
+        // no pack, english messages with the language's numbers
+        let russian = packs.get(Language::Ru);
+        assert_eq!(russian.language, Language::Ru);
+        assert_eq!(russian.number_format.decimal, ',');
+        assert!(packs.languages().contains(&Language::Kr));
+    }
+
+    #[test]
+    fn invalid_template() {
+        let mut pack = LocalePack::english();
+        pack.messages[0].body = "{item}(".to_string();
+        assert!(ChatParser::new(&pack).is_err());
+    }
+}
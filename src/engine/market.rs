@@ -0,0 +1,162 @@
+use serde::{Deserialize, Serialize};
+
+use crate::engine::Trade;
+
+const HOUR_MILLIS: u64 = 60 * 60 * 1000;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PriceStrategy {
+    // most recent trade point
+    Latest,
+    VolumeWeightedAverage,
+    // median of the points in the last `hours`, counted from the most recent point
+    Median { hours: u32 },
+    Min,
+    Max,
+}
+
+/// How a market price series becomes the buy and sell price of an item.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct MarketValuation {
+    pub buy: PriceStrategy,
+    pub sell: PriceStrategy,
+    // points without a single trade only show the listed price, not what it sold for
+    pub ignore_zero_volume: bool,
+}
+
+impl Default for MarketValuation {
+    fn default() -> Self {
+        Self {
+            buy: PriceStrategy::Latest,
+            sell: PriceStrategy::Median { hours: 24 },
+            ignore_zero_volume: true,
+        }
+    }
+}
+
+impl MarketValuation {
+    // (buy, sell), None when there is no usable point
+    pub fn evaluate(&self, trades: &[Trade]) -> (Option<u64>, Option<u64>) {
+        let points: Vec<&Trade> = trades
+            .iter()
+            .filter(|t| t.price > 0 && !(self.ignore_zero_volume && t.volume == 0))
+            .collect();
+        (evaluate(self.buy, &points), evaluate(self.sell, &points))
+    }
+}
+
+pub fn evaluate(strategy: PriceStrategy, points: &[&Trade]) -> Option<u64> {
+    match strategy {
+        PriceStrategy::Latest => points.iter().max_by_key(|t| t.time).map(|t| t.price),
+        PriceStrategy::Min => points.iter().map(|t| t.price).min(),
+        PriceStrategy::Max => points.iter().map(|t| t.price).max(),
+        PriceStrategy::VolumeWeightedAverage => {
+            if points.is_empty() {
+                return None;
+            }
+            let volume: u128 = points.iter().map(|t| t.volume as u128).sum();
+            if volume == 0 {
+                let total: u128 = points.iter().map(|t| t.price as u128).sum();
+                return Some((total / points.len() as u128) as u64);
+            }
+            let total: u128 = points
+                .iter()
+                .map(|t| t.price as u128 * t.volume as u128)
+                .sum();
+            Some((total / volume) as u64)
+        }
+        PriceStrategy::Median { hours } => {
+            let latest = points.iter().map(|t| t.time).max()?;
+            let since = latest.saturating_sub(hours as u64 * HOUR_MILLIS);
+            let mut prices: Vec<u64> = points
+                .iter()
+                .filter(|t| t.time >= since)
+                .map(|t| t.price)
+                .collect();
+            prices.sort_unstable();
+            let mid = prices.len() / 2;
+            if prices.len() % 2 == 1 {
+                Some(prices[mid])
+            } else {
+                Some(((prices[mid - 1] as u128 + prices[mid] as u128) / 2) as u64)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_market {
+    use crate::engine::item_fetcher::BdolyticsMarketAnalytics;
+    use crate::engine::market::{MarketValuation, PriceStrategy};
+
+    // hand written in the shape of a bdolytics analytics answer, not a captured response
+    fn fixture() -> BdolyticsMarketAnalytics {
+        serde_json::from_str(include_str!("../../fixtures/bdolytics/analytics.json")).unwrap()
+    }
+
+    fn valuation(strategy: PriceStrategy, ignore_zero_volume: bool) -> Option<u64> {
+        MarketValuation {
+            buy: strategy,
+            sell: strategy,
+            ignore_zero_volume,
+        }
+        .evaluate(&fixture().data)
+        .1
+    }
+
+    #[test]
+    fn latest() {
+        assert_eq!(valuation(PriceStrategy::Latest, false), Some(2_500_000));
+        // newest point had no trade
+        assert_eq!(valuation(PriceStrategy::Latest, true), Some(2_490_000));
+    }
+
+    #[test]
+    fn volume_weighted_average() {
+        assert_eq!(
+            valuation(PriceStrategy::VolumeWeightedAverage, true),
+            Some(2_449_818)
+        );
+    }
+
+    #[test]
+    fn median_over_hours() {
+        assert_eq!(
+            valuation(PriceStrategy::Median { hours: 3 }, true),
+            Some(2_490_000)
+        );
+        assert_eq!(
+            valuation(PriceStrategy::Median { hours: 3 }, false),
+            Some(2_505_000)
+        );
+        assert_eq!(
+            valuation(PriceStrategy::Median { hours: 24 }, true),
+            Some(2_480_000)
+        );
+    }
+
+    #[test]
+    fn min_max() {
+        assert_eq!(valuation(PriceStrategy::Min, true), Some(2_400_000));
+        assert_eq!(valuation(PriceStrategy::Max, true), Some(2_520_000));
+    }
+
+    #[test]
+    fn separate_buy_sell() {
+        let (buy, sell) = MarketValuation {
+            buy: PriceStrategy::Max,
+            sell: PriceStrategy::Min,
+            ignore_zero_volume: true,
+        }
+        .evaluate(&fixture().data);
+        assert_eq!(buy, Some(2_520_000));
+        assert_eq!(sell, Some(2_400_000));
+    }
+
+    #[test]
+    fn empty_series() {
+        let (buy, sell) = MarketValuation::default().evaluate(&[]);
+        assert_eq!(buy, None);
+        assert_eq!(sell, None);
+    }
+}
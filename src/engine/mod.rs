@@ -1,3 +1,44 @@
+mod amount;
+pub use amount::*;
 mod blackdesert;
 pub use blackdesert::*;
+mod cached_fetcher;
+pub use cached_fetcher::*;
+mod chat;
+pub use chat::*;
+mod disambiguation;
+pub use disambiguation::*;
+mod expenses;
+pub use expenses::*;
+mod grind_spot;
+pub use grind_spot::*;
+mod http_client;
+pub use http_client::*;
+mod item_db;
+pub use item_db::*;
 mod item_fetcher;
+pub use item_fetcher::{BdolyticsUrls, DefaultFetcher, Fetcher, ItemData, ItemFetcher, Trade};
+mod locale;
+pub use locale::*;
+mod market;
+pub use market::*;
+mod price_rules;
+pub use price_rules::*;
+mod providers;
+pub use providers::*;
+mod region;
+pub use region::*;
+mod session;
+#[cfg(test)]
+mod stub_server;
+pub use session::*;
+mod tracker_actor;
+pub use tracker_actor::*;
+mod name_index;
+pub use name_index::*;
+mod ocr_correction;
+pub use ocr_correction::*;
+mod price_resolver;
+pub use price_resolver::*;
+mod valuation;
+pub use valuation::*;
@@ -0,0 +1,196 @@
+use std::collections::HashMap;
+
+// candidates sharing the most trigrams with the query that get a full edit distance check
+const MAX_CANDIDATES: usize = 32;
+// cut off names shorter than this many characters are too ambiguous to complete
+const MIN_PARTIAL_LEN: usize = 5;
+const PARTIAL_SCORE: f64 = 0.85;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct NameMatch {
+    pub name: String,
+    // 1.0 is an exact match after cleaning
+    pub score: f64,
+}
+
+/// Trigram index over every known item name, used to turn ocr output into a canonical name.
+#[derive(Clone, Debug)]
+pub struct NameIndex {
+    names: Vec<String>,
+    cleaned: Vec<String>,
+    by_cleaned: HashMap<String, usize>,
+    trigrams: HashMap<String, Vec<usize>>,
+    // matches scoring below this are ignored
+    pub min_score: f64,
+}
+
+impl Default for NameIndex {
+    fn default() -> Self {
+        Self {
+            names: vec![],
+            cleaned: vec![],
+            by_cleaned: HashMap::new(),
+            trigrams: HashMap::new(),
+            min_score: 0.6,
+        }
+    }
+}
+
+// lowercase letters and digits only, ocr is unreliable on spaces and punctuation
+fn clean(name: &str) -> String {
+    name.chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+fn trigrams(cleaned: &str) -> Vec<String> {
+    let chars: Vec<char> = format!("  {} ", cleaned).chars().collect();
+    let mut out: Vec<String> = chars.windows(3).map(|w| w.iter().collect()).collect();
+    out.sort();
+    out.dedup();
+    out
+}
+
+impl NameIndex {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn from_names<'a>(names: impl IntoIterator<Item = &'a str>) -> Self {
+        let mut index = Self::new();
+        for name in names {
+            index.insert(name);
+        }
+        index
+    }
+
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.names.iter().map(String::as_str)
+    }
+
+    // names that clean to the same string keep the first one inserted
+    pub fn insert(&mut self, name: &str) {
+        let cleaned = clean(name);
+        if cleaned.is_empty() || self.by_cleaned.contains_key(&cleaned) {
+            return;
+        }
+        let idx = self.names.len();
+        for gram in trigrams(&cleaned) {
+            self.trigrams.entry(gram).or_default().push(idx);
+        }
+        self.by_cleaned.insert(cleaned.clone(), idx);
+        self.names.push(name.trim().to_string());
+        self.cleaned.push(cleaned);
+    }
+
+    pub fn best_match(&self, query: &str) -> Option<NameMatch> {
+        let query = clean(query);
+        if query.is_empty() {
+            return None;
+        }
+        if let Some(idx) = self.by_cleaned.get(&query) {
+            return Some(NameMatch {
+                name: self.names[*idx].clone(),
+                score: 1.0,
+            });
+        }
+        let mut shared: HashMap<usize, usize> = HashMap::new();
+        for gram in trigrams(&query) {
+            for idx in self.trigrams.get(&gram).into_iter().flatten() {
+                *shared.entry(*idx).or_default() += 1;
+            }
+        }
+        let mut candidates: Vec<(usize, usize)> = shared.into_iter().collect();
+        candidates.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        candidates.truncate(MAX_CANDIDATES);
+
+        let mut best: Option<(usize, f64)> = None;
+        for (idx, _) in candidates {
+            let cleaned = &self.cleaned[idx];
+            let mut score = strsim::normalized_damerau_levenshtein(cleaned, &query);
+            // the chat box cuts long names at either end
+            if query.chars().count() >= MIN_PARTIAL_LEN
+                && (cleaned.starts_with(&query) || cleaned.ends_with(&query))
+            {
+                score = score.max(PARTIAL_SCORE);
+            }
+            if best.is_none_or(|(_, s)| score > s) {
+                best = Some((idx, score));
+            }
+        }
+        let (idx, score) = best?;
+        if score < self.min_score {
+            return None;
+        }
+        Some(NameMatch {
+            name: self.names[idx].clone(),
+            score,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test_name_index {
+    use crate::engine::NameIndex;
+
+    fn index() -> NameIndex {
+        NameIndex::from_names([
+            "Black Stone",
+            "Black Stone (Armor)",
+            "Caphras Stone",
+            "Narc Magic Mark",
+            "Swamp Leaves",
+            "Silver",
+        ])
+    }
+
+    #[test]
+    fn exact_after_cleaning() {
+        let found = index().best_match("black  stone").unwrap();
+        assert_eq!(found.name, "Black Stone");
+        assert_eq!(found.score, 1.0);
+    }
+
+    #[test]
+    fn ocr_misreads() {
+        let index = index();
+        assert_eq!(
+            index.best_match("Narc Maqic Mark").unwrap().name,
+            "Narc Magic Mark"
+        );
+        assert_eq!(
+            index.best_match("Caphras St0ne").unwrap().name,
+            "Caphras Stone"
+        );
+        assert_eq!(index.best_match("SiIver").unwrap().name, "Silver");
+        // cut off at the start
+        let cut = index.best_match("amp Leaves").unwrap();
+        assert_eq!(cut.name, "Swamp Leaves");
+        assert!(cut.score >= 0.85);
+    }
+
+    #[test]
+    fn no_match_below_score() {
+        let index = index();
+        assert!(index.best_match("Memory Fragment").is_none());
+        assert!(index.best_match("").is_none());
+        assert!(NameIndex::new().best_match("Silver").is_none());
+    }
+
+    #[test]
+    fn short_hangul_is_not_a_cut_name() {
+        let index = NameIndex::from_names(["블랙스톤", "마력의 정수"]);
+        // two characters but six bytes
+        assert!(index.best_match("스톤").is_none());
+        assert_eq!(index.best_match("랙스톤").unwrap().name, "블랙스톤");
+    }
+}
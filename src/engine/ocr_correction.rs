@@ -0,0 +1,318 @@
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use unicode_normalization::UnicodeNormalization;
+
+use crate::engine::{NumberFormat, parse_amount, parse_silver};
+
+pub const OCR_CONFUSIONS_PATH: &str = "config/ocr_confusions.json";
+
+// confusions tried together on one word before giving up
+const MAX_CONFUSIONS: usize = 2;
+
+/// Characters the ocr mixes up, configurable in `config/ocr_confusions.json`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ConfusionMatrix {
+    // read in an amount, meant as this digit
+    pub digits: HashMap<char, char>,
+    // [read, meant] in a name, tried on words that aren't known, lowercase
+    pub letters: Vec<(String, String)>,
+    // read as the `x` between name and amount
+    pub separators: Vec<char>,
+}
+
+impl Default for ConfusionMatrix {
+    fn default() -> Self {
+        let digits = [
+            ('O', '0'),
+            ('o', '0'),
+            ('D', '0'),
+            ('Q', '0'),
+            ('l', '1'),
+            ('I', '1'),
+            ('i', '1'),
+            ('|', '1'),
+            ('!', '1'),
+            ('Z', '2'),
+            ('z', '2'),
+            ('S', '5'),
+            ('s', '5'),
+            ('G', '6'),
+            ('b', '6'),
+            ('T', '7'),
+            ('B', '8'),
+            ('g', '9'),
+            ('q', '9'),
+        ];
+        let letters = [
+            ("0", "o"),
+            ("1", "l"),
+            ("1", "i"),
+            ("5", "s"),
+            ("8", "b"),
+            ("6", "g"),
+            ("2", "z"),
+            ("4", "a"),
+            ("|", "l"),
+            ("rn", "m"),
+            ("m", "rn"),
+            ("vv", "w"),
+            ("cl", "d"),
+            ("u", "v"),
+            ("v", "u"),
+            ("q", "g"),
+            ("l", "i"),
+            ("i", "l"),
+            ("e", "c"),
+            ("c", "e"),
+        ];
+        Self {
+            digits: digits.into_iter().collect(),
+            letters: letters
+                .into_iter()
+                .map(|(read, meant)| (read.to_string(), meant.to_string()))
+                .collect(),
+            separators: vec!['x', 'X', '×', 'х', 'Х'],
+        }
+    }
+}
+
+impl ConfusionMatrix {
+    // a missing file gives the default matrix
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let data =
+            std::fs::read(path).with_context(|| format!("failed to read {}", path.display()))?;
+        serde_json::from_slice(&data).with_context(|| format!("failed to parse {}", path.display()))
+    }
+
+    pub fn is_separator(&self, c: char) -> bool {
+        // accents the ocr puts on the x
+        let base = c.nfd().next().unwrap_or(c);
+        self.separators.contains(&c) || self.separators.contains(&base)
+    }
+}
+
+/// Fixes ocr confusions in loot lines, amounts become digits and names move toward known words.
+#[derive(Clone, Debug, Default)]
+pub struct OcrCorrector {
+    pub matrix: ConfusionMatrix,
+    pub number_format: NumberFormat,
+    // lowercase words of every known item name
+    words: HashSet<String>,
+}
+
+impl OcrCorrector {
+    pub fn new(matrix: ConfusionMatrix) -> Self {
+        Self {
+            matrix,
+            number_format: NumberFormat::default(),
+            words: HashSet::new(),
+        }
+    }
+
+    pub fn with_words<'a>(mut self, names: impl IntoIterator<Item = &'a str>) -> Self {
+        for name in names {
+            self.insert_words(name);
+        }
+        self
+    }
+
+    pub fn insert_words(&mut self, name: &str) {
+        self.words
+            .extend(name.split_whitespace().map(str::to_lowercase));
+    }
+
+    // "Swamp Leaves x 2" into name and amount, the last separator followed by an amount wins
+    pub fn split_drop_log<'a>(&self, line: &'a str) -> Option<(&'a str, u64)> {
+        line.char_indices()
+            .rev()
+            .filter(|(_, c)| self.matrix.is_separator(*c))
+            .find_map(|(idx, c)| {
+                let amount = self.amount(&line[idx + c.len_utf8()..])?;
+                Some((&line[..idx], amount))
+            })
+    }
+
+    // the whole segment has to read as an amount, misread digits are only fixed when it doesn't
+    pub fn amount(&self, segment: &str) -> Option<u64> {
+        self.fixed(segment, parse_amount)
+    }
+
+    // as `amount`, with "3M" style suffixes
+    pub fn silver(&self, segment: &str) -> Option<u64> {
+        self.fixed(segment, parse_silver)
+    }
+
+    fn fixed(&self, segment: &str, parse: fn(&str, &NumberFormat) -> Option<u64>) -> Option<u64> {
+        if let Some(amount) = parse(segment, &self.number_format) {
+            return Some(amount);
+        }
+        let fixed: String = segment
+            .chars()
+            .map(|c| self.matrix.digits.get(&c).copied().unwrap_or(c))
+            .collect();
+        parse(&fixed, &self.number_format)
+    }
+
+    // lowercase words, unknown words are replaced by the first known word a few confusions away
+    pub fn name(&self, name: &str) -> String {
+        name.split_whitespace()
+            .map(|word| self.word(word))
+            .collect::<Vec<String>>()
+            .join(" ")
+    }
+
+    fn word(&self, word: &str) -> String {
+        let lower = word.to_lowercase();
+        if self.words.contains(&lower) {
+            return lower;
+        }
+        if self.words.is_empty() {
+            return self.digits_to_letters(&lower);
+        }
+        let mut frontier = vec![lower.clone()];
+        let mut seen = HashSet::from([lower.clone()]);
+        for _ in 0..MAX_CONFUSIONS {
+            let mut next = vec![];
+            for candidate in &frontier {
+                for (read, meant) in &self.matrix.letters {
+                    for (pos, _) in candidate.match_indices(read.as_str()) {
+                        let fixed = format!(
+                            "{}{}{}",
+                            &candidate[..pos],
+                            meant,
+                            &candidate[pos + read.len()..]
+                        );
+                        if self.words.contains(&fixed) {
+                            return fixed;
+                        }
+                        if seen.insert(fixed.clone()) {
+                            next.push(fixed);
+                        }
+                    }
+                }
+            }
+            frontier = next;
+        }
+        self.digits_to_letters(&lower)
+    }
+
+    // no known word, digits inside a word are still letters
+    fn digits_to_letters(&self, word: &str) -> String {
+        if !word.chars().any(char::is_alphabetic) {
+            return word.to_string();
+        }
+        word.chars()
+            .map(|c| {
+                if !c.is_ascii_digit() {
+                    return c;
+                }
+                self.matrix
+                    .letters
+                    .iter()
+                    .find(|(read, _)| read.chars().eq([c]))
+                    .and_then(|(_, meant)| meant.chars().next())
+                    .unwrap_or(c)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test_ocr_correction {
+    use crate::engine::{ConfusionMatrix, OcrCorrector};
+
+    fn corrector() -> OcrCorrector {
+        OcrCorrector::default().with_words([
+            "Swamp Leaves",
+            "Black Stone",
+            "Silver",
+            "Narc Magic Mark",
+            "Wax Candle",
+        ])
+    }
+
+    #[test]
+    fn drop_log_lines() {
+        // hand written, built from the confusions in the default matrix
+        let cases: &[(&str, Option<(&str, u64)>)] = &[
+            ("Swamp Leaves x 2", Some(("swamp leaves", 2))),
+            ("SWarnp Leaves x 2", Some(("swamp leaves", 2))),
+            ("Silverx100", Some(("silver", 100))),
+            ("silver x100", Some(("silver", 100))),
+            (" Swamp Leaves x 1", Some(("swamp leaves", 1))),
+            ("Silver x 1O9", Some(("silver", 109))),
+            ("SiIver X lO9", Some(("silver", 109))),
+            ("Black Stone × 7", Some(("black stone", 7))),
+            ("8lack St0ne x 3.", Some(("black stone", 3))),
+            ("Narc Maqic Mark x 1", Some(("narc magic mark", 1))),
+            ("Swamp Leaues x S", Some(("swamp leaves", 5))),
+            ("Wax Candle x 4", Some(("wax candle", 4))),
+            ("Silver x 1,234,567", Some(("silver", 1_234_567))),
+            ("Silver x 1.2K", Some(("silver", 1_200))),
+            ("Silver x |09", Some(("silver", 109))),
+            ("Swamp Leaves x 1|", Some(("swamp leaves", 11))),
+            ("Silver x 1B", Some(("silver", 18))),
+            ("Wax Candle", None),
+            ("CRITICAL", None),
+            ("x 2", Some(("", 2))),
+            ("", None),
+        ];
+        let corrector = corrector();
+        for (line, expected) in cases {
+            let parsed = corrector
+                .split_drop_log(line)
+                .map(|(name, amount)| (corrector.name(name), amount));
+            let expected = expected.map(|(name, amount)| (name.to_string(), amount));
+            assert_eq!(parsed, expected, "{:?}", line);
+        }
+    }
+
+    #[test]
+    fn amounts() {
+        let corrector = OcrCorrector::default();
+        let cases: &[(&str, Option<u64>)] = &[
+            ("7", Some(7)),
+            (" 1O9 ", Some(109)),
+            ("l2", Some(12)),
+            ("|09", Some(109)),
+            ("1|", Some(11)),
+            ("1B", Some(18)),
+            ("B", Some(8)),
+            ("2.", Some(2)),
+            ("1 09", Some(109)),
+            ("Sealed", None),
+            ("", None),
+            ("...", None),
+        ];
+        for (segment, expected) in cases {
+            assert_eq!(corrector.amount(segment), *expected, "{:?}", segment);
+        }
+    }
+
+    #[test]
+    fn names_without_dictionary() {
+        let corrector = OcrCorrector::default();
+        assert_eq!(corrector.name("B1ack  St0ne"), "black stone");
+        assert_eq!(corrector.name("Tier 3"), "tier 3");
+    }
+
+    #[test]
+    fn matrix_from_json() {
+        let matrix: ConfusionMatrix = serde_json::from_str(
+            r#"{ "digits": { "O": "0" }, "letters": [["rn", "m"]], "separators": ["x"] }"#,
+        )
+        .unwrap();
+        let corrector = OcrCorrector::new(matrix).with_words(["Swamp"]);
+        assert_eq!(corrector.split_drop_log("Swarnp x O"), Some(("Swarnp ", 0)));
+        assert_eq!(corrector.name("Swarnp"), "swamp");
+        assert_eq!(corrector.amount("S"), None);
+    }
+}
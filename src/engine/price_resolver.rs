@@ -0,0 +1,80 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::sync::{Semaphore, mpsc};
+
+use crate::engine::{BlackDesertLootTracker, Fetcher, Item, PriceRules, TrackerMessage};
+
+#[derive(Clone, Copy, Debug)]
+pub struct PriceResolverConfig {
+    // lookups per item before it's marked unknown
+    pub max_attempts: u32,
+    // delay after the first failure, doubled every attempt
+    pub initial_delay: Duration,
+    pub max_delay: Duration,
+    // lookups running at the same time
+    pub concurrency: usize,
+}
+
+impl Default for PriceResolverConfig {
+    fn default() -> Self {
+        Self {
+            max_attempts: 5,
+            initial_delay: Duration::from_secs(2),
+            max_delay: Duration::from_secs(60),
+            concurrency: 4,
+        }
+    }
+}
+
+/// Background price lookup with retry.
+/// every queued name ends with exactly one `PriceResolved`, `item: None` after the last failed attempt.
+#[derive(Clone)]
+pub struct PriceResolver {
+    fetcher: Arc<Fetcher>,
+    rules: Arc<PriceRules>,
+    config: PriceResolverConfig,
+    permits: Arc<Semaphore>,
+}
+
+impl PriceResolver {
+    pub fn new(fetcher: Arc<Fetcher>, rules: Arc<PriceRules>, config: PriceResolverConfig) -> Self {
+        Self {
+            fetcher,
+            rules,
+            permits: Arc::new(Semaphore::new(config.concurrency.max(1))),
+            config,
+        }
+    }
+
+    pub fn queue(&self, name: String, reply: mpsc::WeakSender<TrackerMessage>) {
+        let resolver = self.clone();
+        tokio::spawn(async move {
+            let item = resolver.resolve(&name).await;
+            if let Some(reply) = reply.upgrade() {
+                _ = reply
+                    .send(TrackerMessage::PriceResolved { name, item })
+                    .await;
+            }
+        });
+    }
+
+    async fn resolve(&self, name: &str) -> Option<Item> {
+        let mut delay = self.config.initial_delay;
+        for attempt in 1..=self.config.max_attempts.max(1) {
+            let item = {
+                // only hold the permit while the request is running, not while waiting to retry
+                let _permit = self.permits.acquire().await.ok()?;
+                BlackDesertLootTracker::find_loot_metadata(&self.fetcher, &self.rules, name).await
+            };
+            if item.is_some() {
+                return item;
+            }
+            if attempt < self.config.max_attempts {
+                tokio::time::sleep(delay).await;
+                delay = (delay * 2).min(self.config.max_delay);
+            }
+        }
+        None
+    }
+}
@@ -0,0 +1,235 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+
+pub const PRICE_RULES_PATH: &str = "config/price_rules.json";
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RuleMatch {
+    #[default]
+    Exact,
+    Contains,
+    Regex,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PriceAction {
+    // pinned price per piece, no lookup
+    Fixed(u64),
+    // always the vendor price even when the item is on the market
+    Vendor,
+    // always the market price, 0 when it has none
+    Market,
+    // event and bound items, not worth anything
+    Zero,
+    // valued as `amount` of another item, e.g. a box at its contents
+    SameAs {
+        item: String,
+        #[serde(default = "one")]
+        amount: u64,
+    },
+}
+
+fn one() -> u64 {
+    1
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PriceRule {
+    // item name, a part of it or a regex depending on `match`
+    pub name: String,
+    #[serde(default, rename = "match")]
+    pub matching: RuleMatch,
+    pub price: PriceAction,
+}
+
+/// Per-item valuation rules, the first rule matching the loot name is applied.
+#[derive(Clone, Debug)]
+pub struct PriceRules {
+    rules: Vec<PriceRule>,
+    // compiled `RuleMatch::Regex` patterns, same index as `rules`
+    patterns: Vec<Option<Regex>>,
+    path: PathBuf,
+}
+
+impl Default for PriceRules {
+    fn default() -> Self {
+        Self::new(default_rules()).unwrap()
+    }
+}
+
+// what used to be hardcoded in the bdolytics fetcher
+fn default_rules() -> Vec<PriceRule> {
+    vec![
+        PriceRule {
+            name: "Silver".to_string(),
+            matching: RuleMatch::Exact,
+            price: PriceAction::Fixed(1),
+        },
+        PriceRule {
+            name: "event".to_string(),
+            matching: RuleMatch::Contains,
+            price: PriceAction::Zero,
+        },
+    ]
+}
+
+impl PriceRules {
+    pub fn new(rules: Vec<PriceRule>) -> Result<Self> {
+        let patterns = rules
+            .iter()
+            .map(|rule| match rule.matching {
+                RuleMatch::Regex => RegexBuilder::new(&rule.name)
+                    .case_insensitive(true)
+                    .build()
+                    .map(Some)
+                    .with_context(|| format!("invalid rule pattern {:?}", rule.name)),
+                _ => Ok(None),
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self {
+            rules,
+            patterns,
+            path: PathBuf::from(PRICE_RULES_PATH),
+        })
+    }
+
+    // a missing file gives the default rules
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let mut rules = if path.exists() {
+            let data = std::fs::read(path)
+                .with_context(|| format!("failed to read {}", path.display()))?;
+            let rules: Vec<PriceRule> = serde_json::from_slice(&data)
+                .with_context(|| format!("failed to parse {}", path.display()))?;
+            Self::new(rules)?
+        } else {
+            Self::default()
+        };
+        rules.path = path.to_path_buf();
+        Ok(rules)
+    }
+
+    pub async fn save(&self) -> Result<()> {
+        let data = serde_json::to_vec_pretty(&self.rules)?;
+        if let Some(dir) = self.path.parent() {
+            tokio::fs::create_dir_all(dir).await?;
+        }
+        tokio::fs::write(&self.path, data).await?;
+        Ok(())
+    }
+
+    pub fn rules(&self) -> &[PriceRule] {
+        &self.rules
+    }
+
+    pub fn push(&mut self, rule: PriceRule) -> Result<()> {
+        let mut rules = std::mem::take(&mut self.rules);
+        rules.push(rule);
+        let path = std::mem::take(&mut self.path);
+        *self = Self::new(rules)?;
+        self.path = path;
+        Ok(())
+    }
+
+    pub fn find(&self, item_name: &str) -> Option<&PriceRule> {
+        let name = item_name.trim().to_lowercase();
+        self.rules
+            .iter()
+            .zip(self.patterns.iter())
+            .find(|(rule, pattern)| match rule.matching {
+                RuleMatch::Exact => rule.name.to_lowercase() == name,
+                RuleMatch::Contains => name.contains(&rule.name.to_lowercase()),
+                RuleMatch::Regex => pattern.as_ref().is_some_and(|p| p.is_match(&name)),
+            })
+            .map(|(rule, _)| rule)
+    }
+}
+
+#[cfg(test)]
+mod test_price_rules {
+    use crate::engine::{
+        BlackDesertLootTracker, Fetcher, ItemData, PriceAction, PriceRule, PriceRules, RuleMatch,
+        StaticPriceFetcher,
+    };
+
+    const RULES: &str = r#"[
+        {"name": "Silver", "price": {"fixed": 1}},
+        {"name": "event", "match": "contains", "price": "zero"},
+        {"name": "Black Stone", "price": "vendor"},
+        {"name": "^hunter's seal", "match": "regex", "price": "market"},
+        {"name": "Box of Black Stones", "price": {"same_as": {"item": "Black Stone", "amount": 5}}},
+        {"name": "Endless Box", "price": {"same_as": {"item": "Black Stone", "amount": 18446744073709551615}}}
+    ]"#;
+
+    fn fetcher() -> Fetcher {
+        let item = |id, name: &str, vendor, market| ItemData {
+            id,
+            name: name.to_string(),
+            vendor_sell_price: vendor,
+            market_sell_price: market,
+            ..Default::default()
+        };
+        Fetcher::Static(StaticPriceFetcher::new(vec![
+            item(16001, "Black Stone", 1_000, 150_000),
+            item(721003, "Hunter's Seal", 100, 0),
+            item(44195, "Swamp Leaves", 50, 300),
+        ]))
+    }
+
+    fn rules() -> PriceRules {
+        PriceRules::new(serde_json::from_str(RULES).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn first_match() {
+        let rules = rules();
+        assert_eq!(rules.find("silver").unwrap().price, PriceAction::Fixed(1));
+        assert_eq!(
+            rules.find("[Event] Cron Stone").unwrap().price,
+            PriceAction::Zero
+        );
+        assert_eq!(
+            rules.find("Hunter's Seal").unwrap().matching,
+            RuleMatch::Regex
+        );
+        assert!(rules.find("Swamp Leaves").is_none());
+        assert!(
+            PriceRules::new(vec![PriceRule {
+                name: "(".to_string(),
+                matching: RuleMatch::Regex,
+                price: PriceAction::Zero,
+            }])
+            .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn applied_before_pricing() {
+        let fetcher = fetcher();
+        let rules = rules();
+        let find = |name: &'static str| {
+            let (fetcher, rules) = (&fetcher, &rules);
+            async move {
+                BlackDesertLootTracker::find_loot_metadata(fetcher, rules, name)
+                    .await
+                    .unwrap()
+            }
+        };
+        assert_eq!(find("Silver").await.price.value(), 1);
+        assert_eq!(find("Event Cron Stone").await.price.value(), 0);
+        assert_eq!(find("Black Stone").await.price.value(), 1_000);
+        let seal = find("Hunter's Seal").await;
+        assert_eq!((seal.price.value(), seal.vendor_price.value()), (0, 0));
+        let box_item = find("Box of Black Stones").await;
+        assert_eq!(box_item.name, "Box of Black Stones");
+        assert_eq!(box_item.price.value(), 750_000);
+        assert_eq!(find("Endless Box").await.price.value(), u64::MAX);
+        // no rule, market price first
+        assert_eq!(find("Swamp Leaves").await.price.value(), 300);
+    }
+}
@@ -0,0 +1,237 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use anyhow::{Context, Result, anyhow};
+use async_trait::async_trait;
+
+use crate::engine::{Fetcher, ItemData, ItemFetcher};
+
+pub const STATIC_PRICES_PATH: &str = "config/prices.json";
+pub const PRICE_OVERRIDES_PATH: &str = "config/price_overrides.json";
+
+fn key(item_name: &str) -> String {
+    item_name.trim().to_lowercase()
+}
+
+fn read_items(path: &Path) -> Result<Vec<ItemData>> {
+    let data = std::fs::read(path).with_context(|| format!("failed to read {}", path.display()))?;
+    serde_json::from_slice(&data).with_context(|| format!("failed to parse {}", path.display()))
+}
+
+/// Fixed prices from a json array of `ItemData`, e.g. an exported price list.
+/// never touches the network, so it's also the provider to use in tests.
+#[derive(Debug, Clone, Default)]
+pub struct StaticPriceFetcher {
+    items: HashMap<String, ItemData>,
+}
+
+impl StaticPriceFetcher {
+    pub fn new(items: Vec<ItemData>) -> Self {
+        Self {
+            items: items
+                .into_iter()
+                .map(|item| (key(&item.name), item))
+                .collect(),
+        }
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        Ok(Self::new(read_items(path.as_ref())?))
+    }
+}
+
+#[async_trait]
+impl ItemFetcher for StaticPriceFetcher {
+    async fn get_data_by_name(&self, item_name: &str) -> Result<ItemData> {
+        self.items
+            .get(&key(item_name))
+            .cloned()
+            .ok_or_else(|| anyhow!("{} has no static price", item_name))
+    }
+}
+
+/// Prices pinned by the user, editable while running and saved back to its file.
+#[derive(Debug, Clone)]
+pub struct OverrideFetcher {
+    items: Arc<RwLock<HashMap<String, ItemData>>>,
+    path: PathBuf,
+}
+
+impl OverrideFetcher {
+    // a missing file starts with no override
+    pub fn load(path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        let items = if path.exists() {
+            read_items(&path)?
+        } else {
+            vec![]
+        };
+        Ok(Self {
+            items: Arc::new(RwLock::new(
+                items
+                    .into_iter()
+                    .map(|item| (key(&item.name), item))
+                    .collect(),
+            )),
+            path,
+        })
+    }
+
+    pub fn set(&self, item: ItemData) {
+        self.items.write().unwrap().insert(key(&item.name), item);
+    }
+
+    pub fn remove(&self, item_name: &str) -> Option<ItemData> {
+        self.items.write().unwrap().remove(&key(item_name))
+    }
+
+    pub async fn save(&self) -> Result<()> {
+        let data = {
+            let items = self.items.read().unwrap();
+            let mut items: Vec<&ItemData> = items.values().collect();
+            items.sort_by(|a, b| a.name.cmp(&b.name));
+            serde_json::to_vec_pretty(&items)?
+        };
+        if let Some(dir) = self.path.parent() {
+            tokio::fs::create_dir_all(dir).await?;
+        }
+        tokio::fs::write(&self.path, data).await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl ItemFetcher for OverrideFetcher {
+    async fn get_data_by_name(&self, item_name: &str) -> Result<ItemData> {
+        self.items
+            .read()
+            .unwrap()
+            .get(&key(item_name))
+            .cloned()
+            .ok_or_else(|| anyhow!("{} has no override", item_name))
+    }
+}
+
+#[derive(Debug)]
+pub struct ChainLink {
+    pub fetcher: Fetcher,
+    pub timeout: Duration,
+}
+
+/// Tries every provider in order and returns the first result,
+/// a provider that errors or takes longer than its timeout is skipped.
+#[derive(Debug, Default)]
+pub struct ChainFetcher {
+    links: Vec<ChainLink>,
+}
+
+impl ChainFetcher {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, fetcher: Fetcher, timeout: Duration) {
+        self.links.push(ChainLink { fetcher, timeout });
+    }
+
+    pub fn with(mut self, fetcher: Fetcher, timeout: Duration) -> Self {
+        self.push(fetcher, timeout);
+        self
+    }
+
+    pub fn links(&self) -> &[ChainLink] {
+        &self.links
+    }
+}
+
+#[async_trait]
+impl ItemFetcher for ChainFetcher {
+    async fn get_data_by_name(&self, item_name: &str) -> Result<ItemData> {
+        let mut errors = vec![];
+        for (idx, link) in self.links.iter().enumerate() {
+            match tokio::time::timeout(link.timeout, link.fetcher.get_data_by_name(item_name)).await
+            {
+                Ok(Ok(data)) => return Ok(data),
+                Ok(Err(err)) => errors.push(format!("#{}: {}", idx, err)),
+                Err(_) => errors.push(format!("#{}: timed out after {:?}", idx, link.timeout)),
+            }
+        }
+        Err(anyhow!(
+            "no provider found {}: [{}]",
+            item_name,
+            errors.join(", ")
+        ))
+    }
+}
+
+#[cfg(test)]
+mod test_providers {
+    use std::time::Duration;
+
+    use anyhow::Result;
+    use async_trait::async_trait;
+
+    use crate::engine::{
+        ChainFetcher, Fetcher, ItemData, ItemFetcher, OverrideFetcher, StaticPriceFetcher,
+    };
+
+    #[derive(Debug)]
+    struct SlowFetcher;
+
+    #[async_trait]
+    impl ItemFetcher for SlowFetcher {
+        async fn get_data_by_name(&self, item_name: &str) -> Result<ItemData> {
+            tokio::time::sleep(Duration::from_secs(60)).await;
+            Ok(item(item_name, 1))
+        }
+    }
+
+    fn item(name: &str, price: u64) -> ItemData {
+        ItemData {
+            id: 1,
+            name: name.to_string(),
+            vendor_buy_price: 0,
+            vendor_sell_price: price,
+            market_buy_price: 0,
+            market_sell_price: 0,
+        }
+    }
+
+    #[tokio::test]
+    async fn chain_falls_back_in_order() {
+        let overrides = OverrideFetcher::load("does/not/exist.json").unwrap();
+        overrides.set(item("Black Stone", 999));
+        let chain = ChainFetcher::new()
+            .with(Fetcher::Overrides(overrides), Duration::from_secs(1))
+            .with(
+                Fetcher::Static(StaticPriceFetcher::new(vec![
+                    item("Black Stone", 100),
+                    item("Swamp Leaves", 20),
+                ])),
+                Duration::from_secs(1),
+            );
+        let stone = chain.get_data_by_name("black stone").await.unwrap();
+        assert_eq!(stone.vendor_sell_price, 999);
+        let leaves = chain.get_data_by_name("Swamp Leaves").await.unwrap();
+        assert_eq!(leaves.vendor_sell_price, 20);
+        let err = chain.get_data_by_name("Caphras Stone").await.unwrap_err();
+        assert!(err.to_string().contains("#1"));
+    }
+
+    #[tokio::test]
+    async fn chain_skips_slow_provider() {
+        let chain = ChainFetcher::new()
+            .with(
+                Fetcher::Custom(Box::new(SlowFetcher)),
+                Duration::from_millis(50),
+            )
+            .with(
+                Fetcher::Static(StaticPriceFetcher::new(vec![item("Black Stone", 100)])),
+                Duration::from_millis(50),
+            );
+        let stone = chain.get_data_by_name("Black Stone").await.unwrap();
+        assert_eq!(stone.vendor_sell_price, 100);
+    }
+}
@@ -0,0 +1,175 @@
+use std::fmt;
+use std::str::FromStr;
+
+use anyhow::{Error, anyhow};
+use serde::{Deserialize, Serialize};
+
+/// Game server, every server has its own market.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum Region {
+    NA,
+    EU,
+    #[default]
+    SEA,
+    MENA,
+    KR,
+    RU,
+    JP,
+    TH,
+    TW,
+    SA,
+}
+
+impl Region {
+    pub const ALL: [Region; 10] = [
+        Region::NA,
+        Region::EU,
+        Region::SEA,
+        Region::MENA,
+        Region::KR,
+        Region::RU,
+        Region::JP,
+        Region::TH,
+        Region::TW,
+        Region::SA,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Region::NA => "NA",
+            Region::EU => "EU",
+            Region::SEA => "SEA",
+            Region::MENA => "MENA",
+            Region::KR => "KR",
+            Region::RU => "RU",
+            Region::JP => "JP",
+            Region::TH => "TH",
+            Region::TW => "TW",
+            Region::SA => "SA",
+        }
+    }
+
+    // language the game client of this server usually runs in
+    pub fn default_language(&self) -> Language {
+        match self {
+            Region::KR => Language::Kr,
+            Region::RU => Language::Ru,
+            Region::JP => Language::Jp,
+            Region::TH => Language::Th,
+            Region::TW => Language::Tw,
+            Region::SA => Language::Pt,
+            Region::NA | Region::EU | Region::SEA | Region::MENA => Language::En,
+        }
+    }
+}
+
+impl fmt::Display for Region {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Region {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Region::ALL
+            .into_iter()
+            .find(|region| region.as_str().eq_ignore_ascii_case(s.trim()))
+            .ok_or_else(|| anyhow!("unknown region {:?}", s))
+    }
+}
+
+/// Language of item names, also the code used by the item db ("en", "kr", ...).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Language {
+    #[default]
+    En,
+    De,
+    Fr,
+    Es,
+    Pt,
+    Ru,
+    Tr,
+    Kr,
+    Jp,
+    Tw,
+    Th,
+    Id,
+}
+
+impl Language {
+    pub const ALL: [Language; 12] = [
+        Language::En,
+        Language::De,
+        Language::Fr,
+        Language::Es,
+        Language::Pt,
+        Language::Ru,
+        Language::Tr,
+        Language::Kr,
+        Language::Jp,
+        Language::Tw,
+        Language::Th,
+        Language::Id,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Language::En => "en",
+            Language::De => "de",
+            Language::Fr => "fr",
+            Language::Es => "es",
+            Language::Pt => "pt",
+            Language::Ru => "ru",
+            Language::Tr => "tr",
+            Language::Kr => "kr",
+            Language::Jp => "jp",
+            Language::Tw => "tw",
+            Language::Th => "th",
+            Language::Id => "id",
+        }
+    }
+}
+
+impl fmt::Display for Language {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Language {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Language::ALL
+            .into_iter()
+            .find(|language| language.as_str().eq_ignore_ascii_case(s.trim()))
+            .ok_or_else(|| anyhow!("unknown language {:?}", s))
+    }
+}
+
+#[cfg(test)]
+mod test_region {
+    use crate::engine::{Language, Region};
+
+    #[test]
+    fn parse() {
+        assert_eq!("na".parse::<Region>().unwrap(), Region::NA);
+        assert_eq!(" SEA ".parse::<Region>().unwrap(), Region::SEA);
+        assert!("moon".parse::<Region>().is_err());
+        assert_eq!("KR".parse::<Language>().unwrap(), Language::Kr);
+        for region in Region::ALL {
+            assert_eq!(region.to_string().parse::<Region>().unwrap(), region);
+        }
+    }
+
+    #[test]
+    fn serde_matches_code() {
+        assert_eq!(serde_json::to_string(&Region::MENA).unwrap(), "\"MENA\"");
+        assert_eq!(serde_json::to_string(&Language::Jp).unwrap(), "\"jp\"");
+        assert_eq!(Region::KR.default_language(), Language::Kr);
+    }
+}
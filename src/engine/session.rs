@@ -0,0 +1,54 @@
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use tokio::io::AsyncWriteExt;
+
+use crate::engine::{Expense, HourlyCost, LootData};
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SpotChange {
+    pub at: i64,
+    pub grind_spot: String,
+}
+
+// snapshot of a grind session, timestamps are unix millis
+#[derive(Default, Clone, Debug, Serialize, Deserialize)]
+pub struct Session {
+    pub started_at: i64,
+    pub ended_at: i64,
+    // grind spot selected or detected when the session was saved
+    #[serde(default)]
+    pub grind_spot: Option<String>,
+    // every spot of the session in order, for comparing sessions per spot
+    #[serde(default)]
+    pub spot_changes: Vec<SpotChange>,
+    pub loot: Vec<LootData>,
+    pub history: Vec<LootData>,
+    // silver spent, read from the chat
+    #[serde(default)]
+    pub expenses: Vec<Expense>,
+    #[serde(default)]
+    pub hourly_costs: Vec<HourlyCost>,
+    // drops the selected grind spot ignored, misreads or loot from somewhere else
+    #[serde(default)]
+    pub rejected: u32,
+}
+
+impl Session {
+    // write the session as `<dir>/<started_at>.json` and return the file path
+    pub async fn save(&self, dir: impl AsRef<Path>) -> std::io::Result<PathBuf> {
+        let dir = dir.as_ref();
+        tokio::fs::create_dir_all(dir).await?;
+        let path = dir.join(format!("{}.json", self.started_at));
+        let data = serde_json::to_vec_pretty(self)?;
+        let mut file = tokio::fs::File::create(&path).await?;
+        file.write_all(&data).await?;
+        file.flush().await?;
+        Ok(path)
+    }
+
+    pub async fn load(path: impl AsRef<Path>) -> std::io::Result<Self> {
+        let data = tokio::fs::read(path).await?;
+        Ok(serde_json::from_slice(&data)?)
+    }
+}
@@ -0,0 +1,154 @@
+// minimal http server answering canned responses, so the fetchers can be tested without network
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+use tokio::task::JoinHandle;
+
+pub struct StubRequest {
+    pub path: String,
+    pub query: HashMap<String, String>,
+}
+
+pub struct StubResponse {
+    pub status: u16,
+    pub body: String,
+}
+
+impl StubResponse {
+    pub fn ok(body: impl Into<String>) -> Self {
+        Self {
+            status: 200,
+            body: body.into(),
+        }
+    }
+
+    pub fn status(status: u16, body: impl Into<String>) -> Self {
+        Self {
+            status,
+            body: body.into(),
+        }
+    }
+}
+
+// hand written response under fixtures/bdolytics
+pub fn fixture(name: &str) -> String {
+    let path = format!("{}/fixtures/bdolytics/{}", env!("CARGO_MANIFEST_DIR"), name);
+    std::fs::read_to_string(&path).unwrap_or_else(|err| panic!("{}: {}", path, err))
+}
+
+pub struct StubServer {
+    pub base_url: String,
+    requests: Arc<Mutex<Vec<String>>>,
+    task: JoinHandle<()>,
+}
+
+impl StubServer {
+    pub async fn start<F>(handler: F) -> Self
+    where
+        F: Fn(&StubRequest) -> StubResponse + Send + Sync + 'static,
+    {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        let handler = Arc::new(handler);
+        let requests = Arc::new(Mutex::new(vec![]));
+        let log = requests.clone();
+        let task = tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let handler = handler.clone();
+                let log = log.clone();
+                tokio::spawn(async move {
+                    let Some(target) = read_target(&mut stream).await else {
+                        return;
+                    };
+                    log.lock().unwrap().push(target.clone());
+                    let response = handler(&parse_target(&target));
+                    let head = format!(
+                        "HTTP/1.1 {} STUB\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                        response.status,
+                        response.body.len()
+                    );
+                    _ = stream.write_all(head.as_bytes()).await;
+                    _ = stream.write_all(response.body.as_bytes()).await;
+                    _ = stream.shutdown().await;
+                });
+            }
+        });
+        Self {
+            base_url,
+            requests,
+            task,
+        }
+    }
+
+    // raw request targets received so far
+    pub fn requests(&self) -> Vec<String> {
+        self.requests.lock().unwrap().clone()
+    }
+}
+
+impl Drop for StubServer {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+// only GET without body is supported, read the head and return the request target
+async fn read_target(stream: &mut tokio::net::TcpStream) -> Option<String> {
+    let mut buf = vec![];
+    let mut chunk = [0u8; 1024];
+    while !buf.windows(4).any(|w| w == b"\r\n\r\n") {
+        let n = stream.read(&mut chunk).await.ok()?;
+        if n == 0 {
+            return None;
+        }
+        buf.extend_from_slice(&chunk[..n]);
+    }
+    let head = String::from_utf8_lossy(&buf);
+    head.lines()
+        .next()?
+        .split_whitespace()
+        .nth(1)
+        .map(str::to_string)
+}
+
+fn parse_target(target: &str) -> StubRequest {
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    StubRequest {
+        path: decode(path),
+        query: query
+            .split('&')
+            .filter(|pair| !pair.is_empty())
+            .map(|pair| {
+                let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+                (decode(key), decode(value))
+            })
+            .collect(),
+    }
+}
+
+fn decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => out.push(b' '),
+            b'%' => match s
+                .get(i + 1..i + 3)
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+            {
+                Some(b) => {
+                    out.push(b);
+                    i += 2;
+                }
+                None => out.push(b'%'),
+            },
+            b => out.push(b),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
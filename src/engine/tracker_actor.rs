@@ -0,0 +1,240 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use tokio::sync::{mpsc, oneshot, watch};
+
+use crate::engine::{
+    BlackDesertLootTracker, Fetcher, HourlyCost, Item, Language, LootData, LootDetectionMode,
+    PriceResolver, PriceRules, Session,
+};
+
+pub enum TrackerMessage {
+    // ocr texts of one frame and when it was captured, unix millis
+    Insert(Vec<String>, i64),
+    SetDetectionMode(LootDetectionMode),
+    SetItemFetcher(Arc<Fetcher>),
+    SetPriceRules(Arc<PriceRules>),
+    // replies false when there is no spot with that name
+    SetGrindSpot(Option<String>, oneshot::Sender<bool>),
+    DetectGrindSpot,
+    SetHourlyCosts(Vec<HourlyCost>),
+    SetLanguage(Language),
+    GetGrindSpots(oneshot::Sender<Vec<String>>),
+    GetDetectionMode(oneshot::Sender<LootDetectionMode>),
+    GetLoot(oneshot::Sender<HashMap<String, LootData>>),
+    GetSession(oneshot::Sender<Session>),
+    Reset,
+    // queue the loot with unknown price for another lookup
+    RetryUnpriced,
+    // sent back by the price lookup tasks
+    PriceResolved { name: String, item: Option<Item> },
+}
+
+/// Handle to the loot tracker actor.
+/// the tracker is owned by a single task so ocr results never wait behind a price lookup,
+/// lookups run in their own task and report back with `PriceResolved`.
+#[derive(Clone)]
+pub struct LootTrackerHandle {
+    sender: mpsc::Sender<TrackerMessage>,
+}
+
+impl LootTrackerHandle {
+    // every change of the loot table is published into `loot_sender`
+    pub fn spawn(
+        tracker: BlackDesertLootTracker,
+        loot_sender: watch::Sender<HashMap<String, LootData>>,
+    ) -> Self {
+        let (sender, receiver) = mpsc::channel(64);
+        let weak_sender = sender.downgrade();
+        tokio::spawn(async move {
+            run_tracker(tracker, receiver, weak_sender, loot_sender).await;
+        });
+        Self { sender }
+    }
+
+    pub async fn insert(&self, texts: Vec<String>, captured_at: i64) {
+        _ = self
+            .sender
+            .send(TrackerMessage::Insert(texts, captured_at))
+            .await;
+    }
+
+    pub async fn set_detection_mode(&self, mode: LootDetectionMode) {
+        _ = self
+            .sender
+            .send(TrackerMessage::SetDetectionMode(mode))
+            .await;
+    }
+
+    pub async fn set_item_fetcher(&self, fetcher: Fetcher) {
+        _ = self
+            .sender
+            .send(TrackerMessage::SetItemFetcher(Arc::new(fetcher)))
+            .await;
+    }
+
+    pub async fn set_price_rules(&self, rules: PriceRules) {
+        _ = self
+            .sender
+            .send(TrackerMessage::SetPriceRules(Arc::new(rules)))
+            .await;
+    }
+
+    // None accepts loot from anywhere again
+    pub async fn set_grind_spot(&self, name: Option<String>) -> bool {
+        let (tx, rx) = oneshot::channel();
+        _ = self
+            .sender
+            .send(TrackerMessage::SetGrindSpot(name, tx))
+            .await;
+        rx.await.unwrap_or(false)
+    }
+
+    pub async fn detect_grind_spot(&self) {
+        _ = self.sender.send(TrackerMessage::DetectGrindSpot).await;
+    }
+
+    pub async fn set_hourly_costs(&self, costs: Vec<HourlyCost>) {
+        _ = self
+            .sender
+            .send(TrackerMessage::SetHourlyCosts(costs))
+            .await;
+    }
+
+    pub async fn set_language(&self, language: Language) {
+        _ = self
+            .sender
+            .send(TrackerMessage::SetLanguage(language))
+            .await;
+    }
+
+    pub async fn grind_spots(&self) -> Vec<String> {
+        let (tx, rx) = oneshot::channel();
+        _ = self.sender.send(TrackerMessage::GetGrindSpots(tx)).await;
+        rx.await.unwrap_or_default()
+    }
+
+    pub async fn detection_mode(&self) -> LootDetectionMode {
+        let (tx, rx) = oneshot::channel();
+        _ = self.sender.send(TrackerMessage::GetDetectionMode(tx)).await;
+        rx.await.unwrap_or(LootDetectionMode::OCRChatLootViaStream)
+    }
+
+    pub async fn loot(&self) -> HashMap<String, LootData> {
+        let (tx, rx) = oneshot::channel();
+        _ = self.sender.send(TrackerMessage::GetLoot(tx)).await;
+        rx.await.unwrap_or_default()
+    }
+
+    pub async fn session(&self) -> Session {
+        let (tx, rx) = oneshot::channel();
+        _ = self.sender.send(TrackerMessage::GetSession(tx)).await;
+        rx.await.unwrap_or_default()
+    }
+
+    pub async fn reset(&self) {
+        _ = self.sender.send(TrackerMessage::Reset).await;
+    }
+
+    pub async fn retry_unpriced(&self) {
+        _ = self.sender.send(TrackerMessage::RetryUnpriced).await;
+    }
+}
+
+async fn run_tracker(
+    mut tracker: BlackDesertLootTracker,
+    mut receiver: mpsc::Receiver<TrackerMessage>,
+    // weak so the actor stops once every handle is dropped
+    sender: mpsc::WeakSender<TrackerMessage>,
+    loot_sender: watch::Sender<HashMap<String, LootData>>,
+) {
+    let new_resolver = |tracker: &BlackDesertLootTracker| {
+        PriceResolver::new(
+            tracker.item_fetcher.clone(),
+            tracker.price_rules.clone(),
+            tracker.price_resolver,
+        )
+    };
+    let mut resolver = new_resolver(&tracker);
+    while let Some(msg) = receiver.recv().await {
+        match msg {
+            TrackerMessage::Insert(texts, captured_at) => {
+                if tracker.insert(&texts, captured_at).await == 0 {
+                    continue;
+                }
+                for name in tracker.take_unpriced() {
+                    resolver.queue(name, sender.clone());
+                }
+            }
+            TrackerMessage::RetryUnpriced => {
+                for name in tracker.take_unknown_prices() {
+                    resolver.queue(name, sender.clone());
+                }
+            }
+            TrackerMessage::PriceResolved { name, item } => {
+                tracker.resolve_price(&name, item);
+            }
+            TrackerMessage::Reset => tracker.reset().await,
+            TrackerMessage::SetDetectionMode(mode) => {
+                tracker.detection_mode = mode;
+                continue;
+            }
+            TrackerMessage::SetItemFetcher(fetcher) => {
+                // lookups already running finish with the old fetcher
+                tracker.item_fetcher = fetcher;
+                resolver = new_resolver(&tracker);
+                continue;
+            }
+            TrackerMessage::SetPriceRules(rules) => {
+                // only used for lookups queued from now on
+                tracker.price_rules = rules;
+                resolver = new_resolver(&tracker);
+                continue;
+            }
+            TrackerMessage::SetGrindSpot(name, reply) => {
+                let spot = match name {
+                    Some(name) => match tracker.grind_spots.find(&name) {
+                        Some(spot) => Some(spot.clone()),
+                        None => {
+                            _ = reply.send(false);
+                            continue;
+                        }
+                    },
+                    None => None,
+                };
+                tracker.set_grind_spot(spot);
+                _ = reply.send(true);
+                continue;
+            }
+            TrackerMessage::DetectGrindSpot => {
+                tracker.use_spot_detection();
+                continue;
+            }
+            TrackerMessage::SetHourlyCosts(costs) => {
+                tracker.expense_config.hourly = costs;
+                continue;
+            }
+            TrackerMessage::SetLanguage(language) => {
+                tracker.set_language(language);
+                continue;
+            }
+            TrackerMessage::GetGrindSpots(reply) => {
+                _ = reply.send(tracker.grind_spots.names());
+                continue;
+            }
+            TrackerMessage::GetDetectionMode(reply) => {
+                _ = reply.send(tracker.detection_mode);
+                continue;
+            }
+            TrackerMessage::GetLoot(reply) => {
+                _ = reply.send(tracker.get_loot_data().clone());
+                continue;
+            }
+            TrackerMessage::GetSession(reply) => {
+                _ = reply.send(tracker.session().await);
+                continue;
+            }
+        }
+        loot_sender.send_replace(tracker.get_loot_data().clone());
+    }
+}
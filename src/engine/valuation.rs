@@ -0,0 +1,141 @@
+use std::collections::HashSet;
+use std::fmt;
+use std::ops::AddAssign;
+
+use serde::{Deserialize, Serialize};
+
+use crate::engine::{LootData, Silver};
+
+// marketplace cut before any bonus
+pub const MARKET_TAX_RATE: f64 = 0.35;
+// Value Pack gives back 30% of the price after tax
+pub const VALUE_PACK_BONUS: f64 = 0.30;
+// family fame bonus caps at 1.5%
+pub const MAX_FAME_BONUS: f64 = 0.015;
+
+/// What the player actually gets when the loot is sold.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ValuationContext {
+    pub tax_rate: f64,
+    pub value_pack: bool,
+    // 0.005, 0.01 or 0.015 depending on family fame
+    pub fame_bonus: f64,
+    // names of items that can't be sold on the market (trash loot, bound items)
+    pub vendor_only: HashSet<String>,
+}
+
+impl Default for ValuationContext {
+    fn default() -> Self {
+        Self {
+            tax_rate: MARKET_TAX_RATE,
+            value_pack: false,
+            fame_bonus: 0.0,
+            vendor_only: HashSet::new(),
+        }
+    }
+}
+
+impl ValuationContext {
+    // share of the listed price that ends up in the storage
+    pub fn market_rate(&self) -> f64 {
+        let mut bonus = 1.0 + self.fame_bonus.clamp(0.0, MAX_FAME_BONUS);
+        if self.value_pack {
+            bonus += VALUE_PACK_BONUS;
+        }
+        ((1.0 - self.tax_rate.clamp(0.0, 1.0)) * bonus).min(1.0)
+    }
+
+    pub fn is_vendor_only(&self, name: &str) -> bool {
+        self.vendor_only.contains(name)
+    }
+}
+
+#[derive(Copy, Clone, Default, Debug, Serialize, Deserialize)]
+pub struct LootValue {
+    // price used for the loot table, before tax
+    pub gross: Silver,
+    // market price after tax and bonus, vendor price for items without market
+    pub net_market: Silver,
+    pub vendor: Silver,
+}
+
+impl LootValue {
+    pub fn of(loot: &LootData, ctx: &ValuationContext) -> Self {
+        let vendor = loot.amount * loot.vendor_price;
+        let net_market = if loot.market_price.value() == 0 || ctx.is_vendor_only(&loot.name) {
+            vendor
+        } else {
+            (loot.amount * loot.market_price).scale(ctx.market_rate())
+        };
+        Self {
+            gross: loot.calculate(),
+            net_market,
+            vendor,
+        }
+    }
+}
+
+impl AddAssign for LootValue {
+    fn add_assign(&mut self, rhs: Self) {
+        self.gross += rhs.gross;
+        self.net_market += rhs.net_market;
+        self.vendor += rhs.vendor;
+    }
+}
+
+impl fmt::Display for LootValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "gross {} | net {} | vendor {}",
+            self.gross, self.net_market, self.vendor
+        )
+    }
+}
+
+#[cfg(test)]
+mod test_valuation {
+    use crate::engine::{LootData, LootValue, Silver, ValuationContext};
+
+    fn loot(name: &str, amount: u64, market: u64, vendor: u64) -> LootData {
+        LootData {
+            name: name.to_string(),
+            amount,
+            price: Silver::new(if market > 0 { market } else { vendor }),
+            market_price: Silver::new(market),
+            vendor_price: Silver::new(vendor),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn market_rate() {
+        let mut ctx = ValuationContext::default();
+        assert!((ctx.market_rate() - 0.65).abs() < 1e-9);
+        ctx.value_pack = true;
+        assert!((ctx.market_rate() - 0.845).abs() < 1e-9);
+        ctx.fame_bonus = 0.5;
+        // fame bonus is capped
+        assert!((ctx.market_rate() - 0.65 * 1.315).abs() < 1e-9);
+    }
+
+    #[test]
+    fn net_value() {
+        let mut ctx = ValuationContext {
+            value_pack: true,
+            ..Default::default()
+        };
+        let stone = LootValue::of(&loot("Black Stone", 10, 200_000, 1_000), &ctx);
+        assert_eq!(stone.gross.value(), 2_000_000);
+        assert_eq!(stone.net_market.value(), 1_690_000);
+        assert_eq!(stone.vendor.value(), 10_000);
+
+        let mark = LootValue::of(&loot("Narc Magic Mark", 3, 0, 12_600), &ctx);
+        assert_eq!(mark.net_market.value(), 37_800);
+
+        ctx.vendor_only.insert("Black Stone".to_string());
+        let stone = LootValue::of(&loot("Black Stone", 10, 200_000, 1_000), &ctx);
+        assert_eq!(stone.net_market.value(), 10_000);
+    }
+}
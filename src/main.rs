@@ -14,7 +14,7 @@ use crossterm::{
     style::Print,
     terminal::{self, ClearType},
 };
-use fan_bd::engine::{ScreenConfig, Silver};
+use fan_bd::engine::{PriceStatus, Profit, ScreenConfig, ValuationContext};
 use std::io::stdout;
 use tokio::process::Command;
 use tokio::spawn;
@@ -29,21 +29,36 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     // Start the capture loop in background
     core.start().await;
 
+    let pipeline_status = core.get_pipeline_status();
+    let valuation = ValuationContext::default();
     // Get a receiver for loot updates
     let mut stdout = stdout();
     // Main loop - process updates as they come
     loop {
-        let loot_updates = core.get_current_loot().await;
+        let session = core.session().await;
         // println!("{:?}", loot_updates);
         stdout.execute(cursor::MoveTo(0, 0)).unwrap();
         stdout.execute(terminal::Clear(ClearType::All)).unwrap();
-        let mut total_silver = Silver::new(0);
-        for (_, v) in loot_updates {
-            let silver = v.calculate();
-            println!("({}){}: {}. {}", v.id, v.name, v.amount, silver);
-            total_silver += silver;
+        for v in &session.loot {
+            let silver = v.calculate_with(&valuation);
+            let price_note = match v.price_status {
+                PriceStatus::Resolved => "",
+                PriceStatus::Pending => " (price pending)",
+                PriceStatus::Unknown => " (price unknown)",
+            };
+            println!(
+                "({}){}: {}. {}{}",
+                v.id, v.name, v.amount, silver, price_note
+            );
         }
-        println!("total silver: {}", total_silver);
+        for e in &session.expenses {
+            println!("spent {:?} {}: {}", e.kind, e.name, e.silver);
+        }
+        if session.rejected > 0 {
+            println!("ignored drops: {}", session.rejected);
+        }
+        println!("total silver: {}", Profit::of(&session, &valuation));
+        println!("status: {}", *pipeline_status.borrow());
         tokio::select! {
                 // This branch executes when new loot data arrives
 
@@ -51,6 +66,9 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 // Add other events here as needed
                 _ = tokio::signal::ctrl_c() => {
                     println!("Ctrl+C received, shutting down");
+                    if let Err(err) = core.stop().await {
+                        println!("failed to stop: {}", err);
+                    }
                     break;
                 }
 
@@ -4,6 +4,7 @@ use derive_more::From;
 use image::math::Rect;
 use reqwest::{Body, Client, multipart};
 use serde::Deserialize;
+use unicode_normalization::UnicodeNormalization;
 
 pub struct OcrClient {
     client: Client,
@@ -94,11 +95,12 @@ impl OcrClient {
         let result: OcrApiResult = serde_json::from_slice(&bytes)?;
         let mut out = OcrOutput { data: Vec::new() };
         for (_, v) in result.result.iter().enumerate() {
+            // keep every script, the client may run in korean or with accents.
+            // composed form so the same name always compares equal
             let clean: String = v
                 .text
-                .clone()
-                .chars()
-                .filter(|c| c.is_ascii_graphic() || c.is_ascii_whitespace())
+                .nfc()
+                .filter(|c| !c.is_control() || c.is_whitespace())
                 .collect();
             out.data.push(OcrOutputData {
                 text: clean,